@@ -0,0 +1,105 @@
+use std::time::{Duration, SystemTime};
+
+use anyhow::Result;
+use serde::Deserialize;
+
+use crate::{
+    client::JenkinsClient,
+    job::Job,
+    output::Format,
+    status::{format_duration, BuildInfo, BuildResult, BuildSummary, BUILD_TREE},
+};
+
+// Builds are fetched from newest to oldest in pages of this size
+const PAGE_SIZE: usize = 50;
+
+// `builds` is capped at the 100 newest builds, `allBuilds` pages through
+// all of them
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct Builds {
+    #[serde(default)]
+    all_builds: Vec<BuildInfo>,
+}
+
+pub struct BuildFilter {
+    pub results: Vec<BuildResult>,
+    pub since: Option<SystemTime>,
+    pub branch: Option<String>,
+    pub limit: usize,
+}
+
+impl BuildFilter {
+    fn matches(&self, build: &BuildInfo) -> bool {
+        self.results.is_empty() || build.result.is_some_and(|r| self.results.contains(&r))
+    }
+}
+
+pub async fn builds(
+    job: String,
+    filter: &BuildFilter,
+    format: Format,
+    client: &JenkinsClient,
+) -> Result<()> {
+    let mut job = Job::new(&client.resolve(&job)?)?;
+    if let Some(branch) = &filter.branch {
+        // Multibranch projects name branch jobs after the branch, with
+        // slashes escaped, as in `feature%2Ffoo`
+        job = job.child(&branch.replace('%', "%25").replace('/', "%2F"));
+    }
+
+    let mut shown = vec![];
+    let mut start = 0;
+    'pages: while shown.len() < filter.limit {
+        let mut url = job.url()?.join("api/json")?;
+        url.query_pairs_mut().append_pair(
+            "tree",
+            &format!(
+                "allBuilds[{}]{{{},{}}}",
+                BUILD_TREE,
+                start,
+                start + PAGE_SIZE
+            ),
+        );
+        let page: Builds = client.get_json(url).await?;
+        if page.all_builds.is_empty() {
+            break;
+        }
+        start += page.all_builds.len();
+
+        for build in page.all_builds {
+            if filter.since.is_some_and(|since| build.started() < since) {
+                break 'pages;
+            }
+            if !filter.matches(&build) {
+                continue;
+            }
+            shown.push(build.summary(&job));
+            if shown.len() == filter.limit {
+                break 'pages;
+            }
+        }
+    }
+
+    format.print(&shown, |shown| {
+        for build in shown {
+            println!("{}", format_build(build));
+        }
+    })
+}
+
+fn format_build(build: &BuildSummary) -> String {
+    let result = match build.result {
+        Some(r) => r.to_string(),
+        None if build.building => "RUNNING".to_owned(),
+        None => "-".to_owned(),
+    };
+    format!(
+        "#{:<6} {:<9} {} {:>10}  {}",
+        build.number,
+        result,
+        build.started,
+        format_duration(Duration::from_millis(build.duration_ms)),
+        build.causes.join("; ")
+    )
+}
@@ -1,8 +1,18 @@
+use std::time::{Duration, SystemTime};
+
 use anyhow::bail;
 use anyhow::Result;
-use clap::{Args, Parser, Subcommand};
+use clap::{builder::RangedU64ValueParser, Args, Parser, Subcommand};
 use reqwest::Url;
 
+use crate::{
+    input::InputDecision,
+    job::Permalink,
+    output::Format,
+    status::BuildResult,
+    tail::{Prefix, DEFAULT_MAX_CONCURRENT_TAILS},
+};
+
 #[derive(Parser, Debug)]
 #[command(arg_required_else_help = true)]
 pub struct JenkinsArgs {
@@ -18,6 +28,9 @@ pub struct JenkinsArgs {
     #[arg(short, long, env = "JENKINS_CLI_CONFIG_PATH")]
     pub config_path: Option<String>,
 
+    #[arg(short, long, value_enum, default_value_t = Format::Text, global = true)]
+    pub output: Format,
+
     #[command(subcommand)]
     pub action: Option<Action>,
 }
@@ -27,6 +40,13 @@ pub enum Action {
     Run(RunArgs),
     Tail(TailArgs),
     Params(ParamsArgs),
+    Status(StatusArgs),
+    Stop(StopArgs),
+    Jobs(JobsArgs),
+    Builds(BuildsArgs),
+    Rebuild(RebuildArgs),
+    Stages(StagesArgs),
+    Input(InputArgs),
 }
 
 #[derive(Args, Debug)]
@@ -34,6 +54,46 @@ pub enum Action {
 pub struct TailArgs {
     #[arg()]
     pub job_url: String,
+
+    #[arg(long, default_value_t = false, conflicts_with = "no_follow")]
+    pub follow_job: bool,
+
+    #[arg(short = 'n', long, conflicts_with = "start_byte")]
+    pub lines: Option<usize>,
+
+    #[arg(long)]
+    pub start_byte: Option<u64>,
+
+    #[arg(long, default_value_t = false)]
+    pub no_follow: bool,
+
+    #[arg(long, value_enum, default_value_t = Prefix::Full)]
+    pub prefix: Prefix,
+
+    #[arg(long, default_value_t = false)]
+    pub tree: bool,
+
+    #[arg(long, default_value_t = false)]
+    pub group: bool,
+
+    #[arg(
+        long,
+        default_value_t = DEFAULT_MAX_CONCURRENT_TAILS,
+        value_parser = RangedU64ValueParser::<usize>::new().range(1..)
+    )]
+    pub max_concurrent_tails: usize,
+
+    #[arg(long, conflicts_with = "no_subjobs")]
+    pub depth: Option<usize>,
+
+    #[arg(long, default_value_t = false)]
+    pub no_subjobs: bool,
+
+    #[arg(long, conflicts_with_all = ["lines", "start_byte"])]
+    pub stage: Option<String>,
+
+    #[arg(long, default_value_t = false, conflicts_with_all = ["lines", "start_byte"])]
+    pub by_stage: bool,
 }
 
 #[derive(Args, Debug)]
@@ -41,6 +101,88 @@ pub struct TailArgs {
 pub struct ParamsArgs {
     #[arg()]
     pub job_url: String,
+
+    #[arg(long, default_value_t = false)]
+    pub definitions: bool,
+}
+
+#[derive(Args, Debug)]
+#[command(arg_required_else_help = true)]
+pub struct StatusArgs {
+    #[arg()]
+    pub job_url: String,
+
+    #[arg(short, long, value_enum)]
+    pub build: Option<Permalink>,
+}
+
+#[derive(Args, Debug)]
+#[command(arg_required_else_help = true)]
+pub struct StagesArgs {
+    #[arg()]
+    pub job_url: String,
+
+    #[arg(long, default_value_t = false)]
+    pub failed_logs: bool,
+}
+
+#[derive(Args, Debug)]
+#[command(arg_required_else_help = true)]
+pub struct StopArgs {
+    #[arg()]
+    pub job_url: String,
+
+    #[arg(long, default_value_t = false)]
+    pub term: bool,
+
+    #[arg(long, default_value_t = false)]
+    pub kill: bool,
+
+    #[arg(long, value_parser = humantime::parse_duration, default_value = "10s")]
+    pub grace: Duration,
+}
+
+#[derive(Args, Debug)]
+pub struct JobsArgs {
+    #[arg()]
+    pub folder_url: Option<String>,
+
+    #[arg(short, long, default_value_t = false)]
+    pub recursive: bool,
+
+    #[arg(short, long)]
+    pub depth: Option<usize>,
+
+    #[arg(short, long)]
+    pub name: Option<String>,
+}
+
+// Accepts either a time ago, like `2h` or `3days`, or a timestamp, like
+// `2023-02-10 12:00:00`
+fn parse_since(since: &str) -> Result<SystemTime> {
+    if let Ok(ago) = humantime::parse_duration(since) {
+        return Ok(SystemTime::now() - ago);
+    }
+    Ok(humantime::parse_rfc3339_weak(since)?)
+}
+
+#[derive(Args, Debug)]
+#[command(arg_required_else_help = true)]
+pub struct BuildsArgs {
+    #[arg()]
+    pub job_url: String,
+
+    #[arg(long, value_enum, ignore_case = true)]
+    pub result: Vec<BuildResult>,
+
+    #[arg(long, value_parser = parse_since)]
+    pub since: Option<SystemTime>,
+
+    #[arg(long)]
+    pub branch: Option<String>,
+
+    #[arg(short = 'n', long, default_value_t = 20)]
+    pub limit: usize,
 }
 
 fn parse_param(param: &str) -> Result<(String, String)> {
@@ -61,6 +203,63 @@ pub struct RunArgs {
 
     #[arg(value_parser=parse_param)]
     pub params: Vec<(String, String)>,
+
+    // Parameters given on the command line override the ones in the file
+    #[arg(long)]
+    pub params_file: Option<String>,
+
+    #[arg(long, default_value_t = false)]
+    pub force: bool,
+
+    #[command(flatten)]
+    pub wait: WaitArgs,
+}
+
+// How commands that trigger a build follow it
+#[derive(Args, Debug)]
+pub struct WaitArgs {
+    #[arg(long, default_value_t = false)]
+    pub no_wait: bool,
+
+    #[arg(long, value_parser = humantime::parse_duration)]
+    pub queue_timeout: Option<Duration>,
+
+    #[arg(long, default_value_t = false)]
+    pub abort_on_interrupt: bool,
+}
+
+#[derive(Args, Debug)]
+#[command(arg_required_else_help = true)]
+pub struct RebuildArgs {
+    #[arg()]
+    pub build_url: String,
+
+    #[arg(value_parser=parse_param)]
+    pub params: Vec<(String, String)>,
+
+    #[arg(long, default_value_t = false)]
+    pub force: bool,
+
+    #[command(flatten)]
+    pub wait: WaitArgs,
+}
+
+#[derive(Args, Debug)]
+#[command(arg_required_else_help = true)]
+pub struct InputArgs {
+    #[arg(value_enum)]
+    pub decision: InputDecision,
+
+    #[arg()]
+    pub build_url: String,
+
+    // Parameters of the input, when approving it
+    #[arg(value_parser=parse_param)]
+    pub params: Vec<(String, String)>,
+
+    // Only needed when the build waits for more than one input
+    #[arg(long)]
+    pub id: Option<String>,
 }
 
 #[cfg(test)]
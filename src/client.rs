@@ -0,0 +1,232 @@
+use std::sync::Arc;
+
+use anyhow::{anyhow, bail, Result};
+use reqwest::{
+    header::{HeaderName, HeaderValue, COOKIE, SET_COOKIE},
+    Client, RequestBuilder, Response, StatusCode, Url,
+};
+use serde::{de::DeserializeOwned, Deserialize};
+use tokio::sync::Mutex;
+
+use crate::config::Profile;
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct CrumbResponse {
+    crumb: String,
+    crumb_request_field: String,
+}
+
+// A CSRF crumb, which is only valid together with the session it was
+// issued for
+#[derive(Debug, Clone)]
+struct Crumb {
+    field: String,
+    value: String,
+    cookie: Option<String>,
+}
+
+// An authenticated connection to the Jenkins server of a profile
+#[derive(Debug, Clone)]
+pub struct JenkinsClient {
+    client: Client,
+    profile: Profile,
+    secret: String,
+    base_url: Url,
+    // None until fetched, Some(None) if the crumb issuer is disabled
+    crumb: Arc<Mutex<Option<Option<Crumb>>>>,
+}
+
+pub async fn resp_error(resp: Response, msg: &str) -> Result<String> {
+    Ok(format!(
+        "{}: status: {:?}, headers: {:?}, body: {:?}",
+        msg,
+        resp.status(),
+        resp.headers().clone(),
+        resp.text().await?
+    ))
+}
+
+async fn check(resp: Response) -> Result<Response> {
+    if !resp.status().is_success() {
+        bail!(resp_error(resp, "Unexpected response").await?);
+    }
+    Ok(resp)
+}
+
+impl JenkinsClient {
+    pub fn new(profile: &Profile) -> Result<JenkinsClient> {
+        Ok(JenkinsClient {
+            client: Client::new(),
+            profile: profile.clone(),
+            secret: profile.secret()?,
+            base_url: profile.url()?,
+            crumb: Arc::new(Mutex::new(None)),
+        })
+    }
+
+    pub fn profile(&self) -> &Profile {
+        &self.profile
+    }
+
+    pub fn base_url(&self) -> &Url {
+        &self.base_url
+    }
+
+    // Urls given on the command line can be absolute, or paths relative
+    // to the profile url
+    pub fn resolve(&self, url: &str) -> Result<Url> {
+        if url.starts_with('/') {
+            Ok(self.base_url.join(url)?)
+        } else {
+            Ok(Url::parse(url)?)
+        }
+    }
+
+    pub fn get(&self, url: Url) -> RequestBuilder {
+        self.client
+            .get(url)
+            .basic_auth(&self.profile.username, Some(&self.secret))
+    }
+
+    pub fn head(&self, url: Url) -> RequestBuilder {
+        self.client
+            .head(url)
+            .basic_auth(&self.profile.username, Some(&self.secret))
+    }
+
+    // Mutating requests carry the CSRF crumb of the profile, if the server
+    // issues one
+    pub async fn post(&self, url: Url) -> Result<RequestBuilder> {
+        let mut req = self
+            .client
+            .post(url)
+            .basic_auth(&self.profile.username, Some(&self.secret));
+        if let Some(crumb) = self.crumb().await? {
+            req = req.header(&crumb.field, &crumb.value);
+            if let Some(cookie) = crumb.cookie {
+                req = req.header(COOKIE, cookie);
+            }
+        }
+        Ok(req)
+    }
+
+    async fn crumb(&self) -> Result<Option<Crumb>> {
+        let mut cached = self.crumb.lock().await;
+        if let Some(crumb) = &*cached {
+            return Ok(crumb.clone());
+        }
+
+        let url = self.base_url.join("crumbIssuer/api/json")?;
+        let resp = self
+            .get(url.clone())
+            .send()
+            .await
+            .map_err(|e| anyhow!("Failed to reach the crumb issuer at {}: {}", url, e))?;
+
+        let crumb = match resp.status() {
+            StatusCode::NOT_FOUND => None,
+            status if status.is_success() => {
+                let cookies: Vec<_> = resp
+                    .headers()
+                    .get_all(SET_COOKIE)
+                    .iter()
+                    .filter_map(|c| c.to_str().ok())
+                    .filter_map(|c| c.split(';').next())
+                    .map(str::to_owned)
+                    .collect();
+                let cookie = if cookies.is_empty() {
+                    None
+                } else {
+                    Some(cookies.join("; "))
+                };
+                let crumb: CrumbResponse = resp.json().await?;
+                Some(Crumb {
+                    field: crumb.crumb_request_field,
+                    value: crumb.crumb,
+                    cookie,
+                })
+            }
+            _ => bail!(resp_error(resp, "Failed to fetch a CSRF crumb").await?),
+        };
+
+        *cached = Some(crumb.clone());
+        Ok(crumb)
+    }
+
+    // Sends a request, turning any non-success response into an error.
+    // Crumbs expire with their session, which is answered with 403, so
+    // requests that carried one are sent again once with a new crumb.
+    pub async fn send(&self, req: RequestBuilder) -> Result<Response> {
+        let req = req.build()?;
+        let retry = req.try_clone();
+        let resp = self.client.execute(req).await?;
+        if resp.status() == StatusCode::FORBIDDEN {
+            let cached = self.crumb.lock().await.clone();
+            match (cached, retry) {
+                (Some(None), _) => bail!(
+                    resp_error(
+                        resp,
+                        "Forbidden, and no CSRF crumb was sent because the crumb issuer is disabled"
+                    )
+                    .await?
+                ),
+                (Some(Some(old)), Some(mut retry)) if retry.headers().get(&old.field).is_some() => {
+                    *self.crumb.lock().await = None;
+                    if let Some(crumb) = self.crumb().await? {
+                        let headers = retry.headers_mut();
+                        headers.insert(
+                            HeaderName::from_bytes(crumb.field.as_bytes())?,
+                            HeaderValue::from_str(&crumb.value)?,
+                        );
+                        match crumb.cookie {
+                            Some(cookie) => {
+                                headers.insert(COOKIE, HeaderValue::from_str(&cookie)?);
+                            }
+                            None => {
+                                headers.remove(COOKIE);
+                            }
+                        }
+                    }
+                    return check(self.client.execute(retry).await?).await;
+                }
+                _ => {}
+            }
+        }
+        check(resp).await
+    }
+
+    pub async fn get_json<T: DeserializeOwned>(&self, url: Url) -> Result<T> {
+        Ok(self.send(self.get(url)).await?.json().await?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn profile(url: &str) -> Result<Profile> {
+        Ok(serde_json::from_value(serde_json::json!({
+            "url": url,
+            "username": "user",
+            "password": "pass",
+        }))?)
+    }
+
+    #[test]
+    fn test_resolve() -> Result<()> {
+        let client = JenkinsClient::new(&profile("http://jenkins.invalid")?)?;
+
+        assert_eq!(
+            client.resolve("/job/x/2")?,
+            Url::parse("http://jenkins.invalid/job/x/2")?
+        );
+        assert_eq!(
+            client.resolve("http://other.invalid/job/x/2")?,
+            Url::parse("http://other.invalid/job/x/2")?
+        );
+        assert!(client.resolve("job/x/2").is_err());
+
+        Ok(())
+    }
+}
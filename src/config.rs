@@ -1,15 +1,20 @@
-use anyhow::{anyhow, bail, Result};
+use anyhow::{anyhow, bail, Context, Result};
 use config::Config;
-use directories::ProjectDirs;
+use directories::{BaseDirs, ProjectDirs};
 use reqwest::Url;
 use serde::Deserialize;
-use std::{collections::HashMap, fmt::Display, path::PathBuf};
+use std::{collections::HashMap, fmt::Display, fs, path::PathBuf, process::Command};
 
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 pub struct Profile {
     pub username: String,
-    pub password: String,
+    // Only one of these is used, in order of precedence: api_token,
+    // password, password_file, password_command
+    pub api_token: Option<String>,
+    pub password: Option<String>,
+    pub password_file: Option<String>,
+    pub password_command: Option<String>,
     url: String,
     #[serde(default)]
     pub aliases: HashMap<String, String>,
@@ -19,6 +24,75 @@ impl Profile {
     pub fn url(&self) -> Result<Url> {
         Ok(Url::parse(&self.url)?)
     }
+
+    // The secret sent along with the username, either an API token or a
+    // password
+    pub fn secret(&self) -> Result<String> {
+        if let Some(token) = &self.api_token {
+            return Ok(token.to_owned());
+        }
+        if let Some(password) = &self.password {
+            return Ok(password.to_owned());
+        }
+        if let Some(path) = &self.password_file {
+            let path = expand_home(path);
+            let password = fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read password_file {}", path.display()))?;
+            return non_empty(password, "password_file");
+        }
+        if let Some(cmd) = &self.password_command {
+            let output = Command::new("sh")
+                .arg("-c")
+                .arg(cmd)
+                .output()
+                .with_context(|| format!("Failed to run password_command `{}`", cmd))?;
+            if !output.status.success() {
+                bail!(
+                    "password_command `{}` failed with {}: {}",
+                    cmd,
+                    output.status,
+                    String::from_utf8_lossy(&output.stderr).trim()
+                );
+            }
+            let password = String::from_utf8(output.stdout)
+                .with_context(|| format!("password_command `{}` printed invalid utf8", cmd))?;
+            return non_empty(password, "password_command");
+        }
+        bail!("Profile has no credentials, set one of api_token, password, password_file or password_command")
+    }
+
+    fn credential_source(&self) -> &str {
+        if self.api_token.is_some() {
+            "api_token"
+        } else if self.password.is_some() {
+            "password"
+        } else if self.password_file.is_some() {
+            "password_file"
+        } else if self.password_command.is_some() {
+            "password_command"
+        } else {
+            "none"
+        }
+    }
+}
+
+// Secrets read from files or commands usually end in a newline, which is
+// not part of the secret
+fn non_empty(secret: String, source: &str) -> Result<String> {
+    let secret = secret.trim_end_matches(['\r', '\n']);
+    if secret.is_empty() {
+        bail!("{} produced an empty password", source);
+    }
+    Ok(secret.to_owned())
+}
+
+fn expand_home(path: &str) -> PathBuf {
+    if let Some(rest) = path.strip_prefix("~/") {
+        if let Some(dirs) = BaseDirs::new() {
+            return dirs.home_dir().join(rest);
+        }
+    }
+    PathBuf::from(path)
 }
 
 #[derive(Debug, Deserialize)]
@@ -81,7 +155,63 @@ impl Display for Profile {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         writeln!(f, "url: {}", self.url)?;
         writeln!(f, "username: {}", self.username)?;
-        writeln!(f, "password: ****")?;
+        writeln!(f, "credentials: {}", self.credential_source())?;
         write!(f, "aliases: {:?}", self.aliases)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn profile(credentials: serde_json::Value) -> Result<Profile> {
+        let mut profile = serde_json::json!({
+            "url": "http://jenkins.invalid",
+            "username": "user",
+        });
+        profile
+            .as_object_mut()
+            .unwrap()
+            .extend(credentials.as_object().unwrap().clone());
+        Ok(serde_json::from_value(profile)?)
+    }
+
+    #[test]
+    fn test_secret_precedence() -> Result<()> {
+        let p = profile(serde_json::json!({
+            "api_token": "token",
+            "password": "password",
+            "password_command": "echo command",
+        }))?;
+        assert_eq!(p.secret()?, "token");
+
+        let p = profile(serde_json::json!({
+            "password": "password",
+            "password_command": "echo command",
+        }))?;
+        assert_eq!(p.secret()?, "password");
+
+        let p = profile(serde_json::json!({ "password_command": "echo command" }))?;
+        assert_eq!(p.secret()?, "command");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_secret_errors() -> Result<()> {
+        assert!(profile(serde_json::json!({}))?.secret().is_err());
+        assert!(profile(serde_json::json!({ "password_command": "false" }))?
+            .secret()
+            .is_err());
+        assert!(profile(serde_json::json!({ "password_command": "true" }))?
+            .secret()
+            .is_err());
+        assert!(
+            profile(serde_json::json!({ "password_file": "/nonexistent/password" }))?
+                .secret()
+                .is_err()
+        );
+
+        Ok(())
+    }
+}
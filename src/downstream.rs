@@ -0,0 +1,263 @@
+use std::collections::HashSet;
+
+use anyhow::Result;
+use serde::Deserialize;
+use url::Url;
+
+use crate::{client::JenkinsClient, job::JobBuild};
+
+// Builds started by multijob phases, by Parameterized Trigger, and by the
+// pipeline `build` step, as reported by their plugins
+const DOWNSTREAM_TREE: &str = "subBuilds[url],\
+    actions[triggeredBuilds[url],downstreamBuilds[jobFullName,buildNumber]]";
+
+const PROJECTS_TREE: &str = "downstreamProjects[url]";
+
+// Downstream projects only link back to the build that started them, so we
+// look for it in the causes of their latest builds
+const CAUSES_TREE: &str = "builds[url,actions[causes[upstreamProject,upstreamBuild]]]{0,10}";
+
+#[derive(Deserialize, Debug)]
+struct Link {
+    url: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct DownstreamBuild {
+    job_full_name: String,
+    // null while the build is in the queue
+    build_number: Option<u32>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct UpstreamCause {
+    upstream_project: Option<String>,
+    upstream_build: Option<u32>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+struct Action {
+    #[serde(default)]
+    triggered_builds: Vec<Link>,
+    #[serde(default)]
+    downstream_builds: Vec<DownstreamBuild>,
+    #[serde(default)]
+    causes: Vec<UpstreamCause>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct BuildLinks {
+    #[serde(default)]
+    sub_builds: Vec<Link>,
+    // Actions without exported fields are null in some versions
+    #[serde(default)]
+    actions: Vec<Option<Action>>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct Project {
+    #[serde(default)]
+    downstream_projects: Vec<Link>,
+}
+
+#[derive(Deserialize, Debug)]
+struct ProjectBuild {
+    url: String,
+    #[serde(default)]
+    actions: Vec<Option<Action>>,
+}
+
+#[derive(Deserialize, Debug)]
+struct ProjectBuilds {
+    #[serde(default)]
+    builds: Vec<ProjectBuild>,
+}
+
+// Finds the builds a build started through the JSON API, which catches the
+// ones that don't log `Starting building:`
+pub struct Downstream {
+    build: JobBuild,
+    // Fetched once, jobs don't change their downstream projects mid-build
+    projects: Option<Vec<Url>>,
+    found: HashSet<JobBuild>,
+}
+
+// Urls from the API are relative, or absolute to the root url Jenkins is
+// configured with, which may not be the one in the profile
+fn build_at(url: &str, client: &JenkinsClient) -> Result<JobBuild> {
+    let url = client.base_url().join(url)?;
+    JobBuild::new(&client.base_url().join(url.path())?)
+}
+
+fn build_of(full_name: &str, number: u32, client: &JenkinsClient) -> Result<JobBuild> {
+    let segments: Vec<_> = full_name.split('/').map(urlencoding::encode).collect();
+    let path = format!("job/{}/{}/", segments.join("/job/"), number);
+    JobBuild::new(&client.base_url().join(&path)?)
+}
+
+impl BuildLinks {
+    fn builds(&self, client: &JenkinsClient) -> Result<Vec<JobBuild>> {
+        let actions = self.actions.iter().flatten();
+        let mut builds = vec![];
+        for link in self
+            .sub_builds
+            .iter()
+            .chain(actions.clone().flat_map(|a| a.triggered_builds.iter()))
+        {
+            if let Some(url) = &link.url {
+                builds.push(build_at(url, client)?);
+            }
+        }
+        for downstream in actions.flat_map(|a| a.downstream_builds.iter()) {
+            if let Some(number) = downstream.build_number {
+                builds.push(build_of(&downstream.job_full_name, number, client)?);
+            }
+        }
+        Ok(builds)
+    }
+}
+
+impl ProjectBuild {
+    fn started_by(&self, build: &JobBuild) -> bool {
+        let name = build.job().full_name();
+        self.actions
+            .iter()
+            .flatten()
+            .flat_map(|a| a.causes.iter())
+            .any(|c| {
+                c.upstream_project.as_deref() == Some(name.as_str())
+                    && c.upstream_build == Some(build.number())
+            })
+    }
+}
+
+impl Downstream {
+    pub fn new(build: &JobBuild) -> Downstream {
+        Downstream {
+            build: build.clone(),
+            projects: None,
+            found: HashSet::new(),
+        }
+    }
+
+    async fn downstream_projects(&self, client: &JenkinsClient) -> Result<Vec<Url>> {
+        let mut api = self.build.job().url()?.join("api/json")?;
+        api.query_pairs_mut().append_pair("tree", PROJECTS_TREE);
+        let project: Project = client.get_json(api).await?;
+        let mut projects = vec![];
+        for link in project.downstream_projects {
+            if let Some(url) = link.url {
+                projects.push(client.base_url().join(&url)?);
+            }
+        }
+        Ok(projects)
+    }
+
+    // The latest builds of a downstream project started by the build
+    async fn started_in(&self, project: &Url, client: &JenkinsClient) -> Result<Vec<JobBuild>> {
+        let mut api = project.join("api/json")?;
+        api.query_pairs_mut().append_pair("tree", CAUSES_TREE);
+        let project: ProjectBuilds = client.get_json(api).await?;
+        project
+            .builds
+            .iter()
+            .filter(|b| b.started_by(&self.build))
+            .map(|b| build_at(&b.url, client))
+            .collect()
+    }
+
+    // Returns the urls of the downstream builds not returned before
+    pub async fn discover(&mut self, client: &JenkinsClient) -> Result<Vec<Url>> {
+        let mut api = self.build.url()?.join("api/json")?;
+        api.query_pairs_mut().append_pair("tree", DOWNSTREAM_TREE);
+        let links: BuildLinks = client.get_json(api).await?;
+        let mut builds = links.builds(client)?;
+
+        // Projects we can't read answer 404, which shouldn't hide the
+        // builds found otherwise
+        if self.projects.is_none() {
+            self.projects = Some(self.downstream_projects(client).await.unwrap_or_default());
+        }
+        for project in self.projects.iter().flatten() {
+            if let Ok(started) = self.started_in(project, client).await {
+                builds.extend(started);
+            }
+        }
+
+        let mut urls = vec![];
+        for build in builds {
+            if build != self.build && self.found.insert(build.clone()) {
+                urls.push(build.url()?);
+            }
+        }
+        Ok(urls)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Profile;
+
+    fn client() -> Result<JenkinsClient> {
+        let profile: Profile = serde_json::from_value(serde_json::json!({
+            "url": "http://jenkins.invalid",
+            "username": "user",
+            "password": "pass",
+        }))?;
+        JenkinsClient::new(&profile)
+    }
+
+    #[test]
+    fn test_build_links() -> Result<()> {
+        let client = client()?;
+        let links: BuildLinks = serde_json::from_value(serde_json::json!({
+            "subBuilds": [{ "url": "job/phase/3/" }],
+            "actions": [
+                null,
+                {},
+                { "triggeredBuilds": [{ "url": "https://ci.example.com/job/a/job/b/7/" }] },
+                {
+                    "downstreamBuilds": [
+                        { "jobFullName": "folder/c", "buildNumber": 12 },
+                        { "jobFullName": "queued", "buildNumber": null },
+                    ]
+                },
+            ],
+        }))?;
+
+        let builds: Vec<_> = links
+            .builds(&client)?
+            .iter()
+            .map(JobBuild::to_string)
+            .collect();
+        assert_eq!(builds, vec!["phase #3", "a » b #7", "folder » c #12"]);
+        assert_eq!(
+            links.builds(&client)?[1].url()?.as_str(),
+            "http://jenkins.invalid/job/a/job/b/7/"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_started_by() -> Result<()> {
+        let upstream = JobBuild::parse("http://jenkins.invalid/job/folder/job/up/4/")?;
+        let build: ProjectBuild = serde_json::from_value(serde_json::json!({
+            "url": "http://jenkins.invalid/job/down/9/",
+            "actions": [
+                { "causes": [{ "upstreamProject": "folder/up", "upstreamBuild": 4 }] },
+            ],
+        }))?;
+
+        assert!(build.started_by(&upstream));
+        assert!(!build.started_by(&upstream.job().build(5)));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,422 @@
+use std::{
+    collections::HashSet,
+    time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, bail, Result};
+use clap::ValueEnum;
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use url::Url;
+
+use crate::{
+    client::{resp_error, JenkinsClient},
+    job::{BuildRef, JobBuild, Permalink},
+    output::Format,
+    params::ParameterKind,
+};
+
+// How often to look for pending inputs while a build is quiet, unless its
+// log says an input step started
+const PROMPT_INTERVAL: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum InputDecision {
+    Approve,
+    Abort,
+}
+
+impl InputDecision {
+    fn name(&self) -> &str {
+        match self {
+            InputDecision::Approve => "approve",
+            InputDecision::Abort => "abort",
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+struct InputDefinition {
+    default_parameter_value: Option<DefaultValue>,
+    #[serde(default)]
+    choices: Vec<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct DefaultValue {
+    #[serde(default)]
+    value: Value,
+}
+
+// A parameter of an input step, as reported by the Pipeline Stage View
+// plugin. `type` is the simple class name of its definition.
+#[derive(Deserialize, Debug)]
+struct InputParameter {
+    #[serde(rename = "type")]
+    class: String,
+    name: String,
+    #[serde(default)]
+    description: Option<String>,
+    #[serde(default)]
+    definition: Option<InputDefinition>,
+}
+
+// An input step a pipeline build is paused on
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct PendingInput {
+    id: String,
+    #[serde(default)]
+    message: String,
+    #[serde(default)]
+    inputs: Vec<InputParameter>,
+}
+
+// An answered input, as printed in the structured output formats
+#[derive(Serialize, Debug)]
+pub struct InputSummary {
+    pub build: String,
+    pub url: String,
+    pub id: String,
+    // approve or abort
+    pub action: String,
+}
+
+// None for builds other than pipelines, which have no inputs and answer 404
+async fn pending_inputs(build: &Url, client: &JenkinsClient) -> Result<Option<Vec<PendingInput>>> {
+    let resp = client
+        .get(build.join("wfapi/pendingInputActions")?)
+        .send()
+        .await?;
+    if resp.status() == StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+    if !resp.status().is_success() {
+        bail!(resp_error(resp, "Unexpected response").await?);
+    }
+    Ok(Some(resp.json().await?))
+}
+
+// Input ids are chosen by the pipeline, so they may need escaping
+fn input_url(build: &Url, id: &str, action: &str) -> Result<Url> {
+    let mut url = build.clone();
+    url.path_segments_mut()
+        .map_err(|_| anyhow!("{} can't be a build url", build))?
+        .pop_if_empty()
+        .extend(["input", id, action]);
+    Ok(url)
+}
+
+impl InputParameter {
+    fn default_value(&self) -> Option<&Value> {
+        let definition = self.definition.as_ref()?;
+        let value = &definition.default_parameter_value.as_ref()?.value;
+        (!value.is_null()).then_some(value)
+    }
+
+    fn describe(&self) -> String {
+        let mut text = format!(
+            "  {} ({})",
+            self.name,
+            ParameterKind::from_class(&self.class)
+        );
+        if let Some(description) = self.description.as_deref().filter(|d| !d.is_empty()) {
+            text.push_str(&format!(": {}", description));
+        }
+        if let Some(definition) = &self.definition {
+            if !definition.choices.is_empty() {
+                text.push_str(&format!(" [choices: {}]", definition.choices.join(", ")));
+            }
+        }
+        match self.default_value() {
+            None => {}
+            Some(Value::String(s)) => text.push_str(&format!(" [default: {}]", s)),
+            Some(other) => text.push_str(&format!(" [default: {}]", other)),
+        }
+        text
+    }
+}
+
+impl PendingInput {
+    // The prompt of the input, with its parameters and how to answer it
+    fn prompt(&self, build: &JobBuild) -> Result<String> {
+        let url = build.url()?;
+        let mut lines = vec![format!(
+            "{} is waiting for input {}: {}",
+            build, self.id, self.message
+        )];
+        let mut approve = format!("jenkins input approve {} --id {}", url, self.id);
+        for input in &self.inputs {
+            lines.push(input.describe());
+            approve.push_str(&format!(" {}=...", input.name));
+        }
+        lines.push(format!(
+            "Answer with `{}` or `jenkins input abort {} --id {}`",
+            approve, url, self.id
+        ));
+        Ok(lines.join("\n"))
+    }
+}
+
+// Shows the prompts of the inputs a build is paused on while tailing it,
+// once each
+pub(crate) struct Prompts {
+    build: JobBuild,
+    shown: HashSet<String>,
+    // Only pipeline builds have inputs
+    unsupported: bool,
+    // Whether the last lookup failed, so failures are only reported once
+    // until one succeeds
+    failing: bool,
+    // When inputs were last looked up
+    checked: Option<Instant>,
+    // Whether the log says an input step started since the last lookup
+    requested: bool,
+}
+
+impl Prompts {
+    pub(crate) fn new(build: &JobBuild) -> Prompts {
+        Prompts {
+            build: build.clone(),
+            shown: HashSet::new(),
+            unsupported: false,
+            failing: false,
+            checked: None,
+            requested: false,
+        }
+    }
+
+    // Input steps log `[Pipeline] input`, and `Input requested` when they
+    // take parameters
+    pub(crate) fn note(&mut self, line: &str) {
+        if line == "[Pipeline] input" || line == "Input requested" {
+            self.requested = true;
+        }
+    }
+
+    pub(crate) async fn show(&mut self, format: Format, client: &JenkinsClient) -> Result<()> {
+        let due = self.checked.is_none_or(|t| t.elapsed() >= PROMPT_INTERVAL);
+        if self.unsupported || !(due || self.requested) {
+            return Ok(());
+        }
+        self.checked = Some(Instant::now());
+        // Errors are retried on the next poll, they shouldn't stop tailing
+        let inputs = match pending_inputs(&self.build.url()?, client).await {
+            Ok(Some(inputs)) => inputs,
+            Ok(None) => {
+                self.unsupported = true;
+                return Ok(());
+            }
+            Err(e) => {
+                if !self.failing {
+                    eprintln!(
+                        "Warning: Failed to get the pending inputs of {}: {}",
+                        self.build, e
+                    );
+                }
+                self.failing = true;
+                return Ok(());
+            }
+        };
+        self.failing = false;
+        self.requested = false;
+        for input in inputs {
+            if self.shown.insert(input.id.clone()) {
+                format.progress(input.prompt(&self.build)?);
+            }
+        }
+        Ok(())
+    }
+}
+
+// Picks the input to answer, which needs an id only if the build is paused
+// on more than one
+fn select<'a>(
+    inputs: &'a [PendingInput],
+    id: Option<&str>,
+    build: &BuildRef,
+) -> Result<&'a PendingInput> {
+    let ids: Vec<_> = inputs.iter().map(|i| i.id.as_str()).collect();
+    match (id, inputs) {
+        (_, []) => bail!("{} isn't waiting for input", build),
+        (None, [input]) => Ok(input),
+        (None, _) => bail!(
+            "{} is waiting for more than one input, pick one with --id: {}",
+            build,
+            ids.join(", ")
+        ),
+        (Some(id), _) => inputs.iter().find(|i| i.id == id).ok_or_else(|| {
+            anyhow!(
+                "{} has no pending input {}. Its pending inputs are: {}",
+                build,
+                id,
+                ids.join(", ")
+            )
+        }),
+    }
+}
+
+// The form Jenkins expects when submitting an input with parameters. Jenkins
+// doesn't fill in the ones left out, so they're sent with their defaults.
+fn submission(input: &PendingInput, params: &[(String, String)]) -> Result<String> {
+    let names: Vec<_> = input.inputs.iter().map(|i| i.name.as_str()).collect();
+    for (name, _) in params {
+        if !names.contains(&name.as_str()) {
+            bail!(
+                "{} is not a parameter of input {}. Its parameters are: {}",
+                name,
+                input.id,
+                names.join(", ")
+            );
+        }
+    }
+
+    let mut parameters = vec![];
+    let mut missing = vec![];
+    for parameter in &input.inputs {
+        // The last value given wins, as in `run`
+        let given = params
+            .iter()
+            .rev()
+            .find(|(name, _)| name == &parameter.name);
+        let value = match (given, parameter.default_value()) {
+            (Some((_, value)), _) => Value::from(value.as_str()),
+            (None, Some(default)) => default.clone(),
+            (None, None) => {
+                missing.push(parameter.name.as_str());
+                continue;
+            }
+        };
+        parameters.push(json!({ "name": parameter.name, "value": value }));
+    }
+    if !missing.is_empty() {
+        bail!(
+            "{} of input {} {} no default, pass {} as NAME=VALUE",
+            missing.join(", "),
+            input.id,
+            if missing.len() == 1 { "has" } else { "have" },
+            if missing.len() == 1 { "it" } else { "them" },
+        );
+    }
+    Ok(json!({ "parameter": parameters }).to_string())
+}
+
+// Approves or aborts an input step of a pipeline build, the last build of
+// the job by default
+pub async fn input(
+    job: String,
+    decision: InputDecision,
+    id: Option<String>,
+    params: Vec<(String, String)>,
+    format: Format,
+    client: &JenkinsClient,
+) -> Result<()> {
+    let build = BuildRef::new(&client.resolve(&job)?, Permalink::LastBuild)?;
+    let url = build.url()?;
+    let inputs = pending_inputs(&url, client)
+        .await
+        .map_err(|e| anyhow!("Failed to get the pending inputs of {}: {}", build, e))?
+        .ok_or_else(|| anyhow!("{} isn't a pipeline build, it has no inputs", build))?;
+    let input = select(&inputs, id.as_deref(), &build)?;
+
+    let req = match decision {
+        InputDecision::Approve if input.inputs.is_empty() && params.is_empty() => {
+            format.progress(format!("Approving input {} of {}...", input.id, build));
+            client
+                .post(input_url(&url, &input.id, "proceedEmpty")?)
+                .await?
+        }
+        InputDecision::Approve => {
+            let form = submission(input, &params)?;
+            format.progress(format!("Approving input {} of {}...", input.id, build));
+            client
+                .post(input_url(&url, &input.id, "proceed")?)
+                .await?
+                .form(&[("json", form)])
+        }
+        InputDecision::Abort if !params.is_empty() => {
+            bail!("Parameters are only taken when approving an input")
+        }
+        InputDecision::Abort => {
+            format.progress(format!("Aborting input {} of {}...", input.id, build));
+            client.post(input_url(&url, &input.id, "abort")?).await?
+        }
+    };
+    client.send(req).await?;
+
+    let summary = InputSummary {
+        build: build.to_string(),
+        url: url.to_string(),
+        id: input.id.clone(),
+        action: decision.name().to_owned(),
+    };
+    format.print(&summary, |_| {})
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pending() -> Result<PendingInput> {
+        Ok(serde_json::from_value(json!({
+            "id": "Deploy",
+            "proceedText": "Proceed",
+            "message": "Deploy to prod?",
+            "inputs": [
+                {
+                    "type": "StringParameterDefinition",
+                    "name": "REASON",
+                    "description": "Why now",
+                    "definition": { "defaultParameterValue": { "value": "release" } },
+                },
+                {
+                    "type": "ChoiceParameterDefinition",
+                    "name": "REGION",
+                    "description": "",
+                    "definition": { "choices": ["eu", "us"] },
+                },
+            ],
+            "proceedUrl": "/job/deploy/12/wfapi/inputSubmit?inputId=Deploy",
+            "abortUrl": "/job/deploy/12/input/Deploy/abort",
+        }))?)
+    }
+
+    #[test]
+    fn test_prompt() -> Result<()> {
+        let build = JobBuild::parse("http://jenkins.invalid/job/deploy/12/")?;
+        assert_eq!(
+            pending()?.prompt(&build)?,
+            "deploy #12 is waiting for input Deploy: Deploy to prod?\n  \
+            REASON (string): Why now [default: release]\n  \
+            REGION (choice) [choices: eu, us]\n\
+            Answer with `jenkins input approve http://jenkins.invalid/job/deploy/12/ --id Deploy \
+            REASON=... REGION=...` or `jenkins input abort http://jenkins.invalid/job/deploy/12/ \
+            --id Deploy`"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_submission() -> Result<()> {
+        let input = pending()?;
+        assert_eq!(
+            submission(&input, &[("REGION".to_owned(), "eu".to_owned())])?,
+            r#"{"parameter":[{"name":"REASON","value":"release"},{"name":"REGION","value":"eu"}]}"#
+        );
+        let err = submission(&input, &[]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "REGION of input Deploy has no default, pass it as NAME=VALUE"
+        );
+        let err = submission(&input, &[("ENV".to_owned(), "prod".to_owned())]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "ENV is not a parameter of input Deploy. Its parameters are: REASON, REGION"
+        );
+
+        Ok(())
+    }
+}
@@ -1,10 +1,11 @@
 use std::{collections::HashMap, fmt::Display};
 
 use anyhow::{bail, Result};
+use clap::ValueEnum;
 use lazy_static::lazy_static;
 use regex::Regex;
 use reqwest::Url;
-use urlencoding::decode;
+use urlencoding::{decode, encode};
 
 lazy_static! {
     // static ref job_re: Regex = Regex::new(r"(job/[^/]+)+/(\d+)/?").unwrap();
@@ -17,10 +18,11 @@ lazy_static! {
     ).unwrap();
 }
 
-type Params = HashMap<String, String>;
+// Build parameters by name. Multi-select parameters can take several values.
+pub type Params = HashMap<String, Vec<String>>;
 
 // A Jenkins job
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Job {
     path: Vec<String>,
     base_url: Url,
@@ -64,8 +66,48 @@ impl Job {
         bail!("Failed to parse job from url: {}", url);
     }
 
+    // The path segments of the job, encoded again as they were decoded, so
+    // names with slashes like multibranch `feature%2Ffoo` keep them escaped
+    fn segments(&self) -> String {
+        self.path
+            .iter()
+            .map(|s| encode(s).into_owned())
+            .collect::<Vec<_>>()
+            .join("/job/")
+    }
+
+    pub fn url(&self) -> Result<Url> {
+        Ok(self.base_url.join(&format!("job/{}/", self.segments()))?)
+    }
+
+    pub fn name(&self) -> &str {
+        self.path.last().map(String::as_str).unwrap_or_default()
+    }
+
+    // The name Jenkins refers to the job by, as in folder/job
+    pub fn full_name(&self) -> String {
+        self.path.join("/")
+    }
+
+    // Jobs nested in a folder or multibranch project
+    pub fn child(&self, name: &str) -> Job {
+        let mut path = self.path.clone();
+        path.push(name.to_owned());
+        Job {
+            path,
+            base_url: self.base_url.clone(),
+        }
+    }
+
+    pub fn build(&self, number: u32) -> JobBuild {
+        JobBuild {
+            job: self.clone(),
+            number,
+        }
+    }
+
     pub fn build_path(&self, params: &Params) -> String {
-        let mut path = format!("job/{}/build", self.path.join("/job/"));
+        let mut path = format!("job/{}/build", self.segments());
         if !params.is_empty() {
             path.push_str("WithParameters");
         }
@@ -80,7 +122,7 @@ impl Display for Job {
 }
 
 // A particular build of a Jenkins job
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct JobBuild {
     job: Job,
     number: u32,
@@ -105,10 +147,22 @@ impl JobBuild {
         bail!("Failed to parse `{}` as a Jenkins job url", url);
     }
 
-    pub fn log_path(&self, start: u32) -> Result<Url> {
+    pub fn job(&self) -> &Job {
+        &self.job
+    }
+
+    pub fn number(&self) -> u32 {
+        self.number
+    }
+
+    pub fn url(&self) -> Result<Url> {
+        Ok(self.job.url()?.join(&format!("{}/", self.number))?)
+    }
+
+    pub fn log_path(&self, start: u64) -> Result<Url> {
         let path = format!(
             "job/{}/{}/logText/progressiveText?start={start}",
-            self.job.path.join("/job/"),
+            self.job.segments(),
             self.number
         );
         Ok(self.job.base_url.join(&path)?)
@@ -117,7 +171,7 @@ impl JobBuild {
     pub fn params_path(&self) -> Result<Url> {
         let path = format!(
             "job/{}/{}/api/json?tree=actions[parameters[name,value]]",
-            self.job.path.join("/job/"),
+            self.job.segments(),
             self.number
         );
         Ok(self.job.base_url.join(&path)?)
@@ -130,6 +184,103 @@ impl Display for JobBuild {
     }
 }
 
+// The builds Jenkins keeps a permanent link to for every job
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Permalink {
+    #[value(name = "lastBuild")]
+    LastBuild,
+    #[value(name = "lastCompletedBuild")]
+    LastCompletedBuild,
+    #[value(name = "lastSuccessfulBuild")]
+    LastSuccessfulBuild,
+    #[value(name = "lastStableBuild")]
+    LastStableBuild,
+    #[value(name = "lastUnstableBuild")]
+    LastUnstableBuild,
+    #[value(name = "lastFailedBuild")]
+    LastFailedBuild,
+    #[value(name = "lastUnsuccessfulBuild")]
+    LastUnsuccessfulBuild,
+}
+
+impl Permalink {
+    // Finds a permalink right after the job segments of a classic url,
+    // as in job/a/job/b/lastBuild or job/a/job/b/lastFailedBuild/console
+    fn from_url(url: &Url) -> Result<Option<Permalink>> {
+        let segments: Vec<_> = url.path().split('/').filter(|s| !s.is_empty()).collect();
+        let mut i = 0;
+        while segments.get(i) == Some(&"job") && i + 1 < segments.len() {
+            i += 2;
+        }
+        let Some(name) = segments.get(i).filter(|_| i > 0) else {
+            return Ok(None);
+        };
+        match Permalink::from_str(name, false) {
+            Ok(permalink) => Ok(Some(permalink)),
+            Err(_) => bail!(
+                "Failed to parse build from url: {}, {} is not a build number or permalink",
+                url,
+                name
+            ),
+        }
+    }
+}
+
+impl Display for Permalink {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.to_possible_value().unwrap().get_name())
+    }
+}
+
+// A build given on the command line, either by number or by permalink
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BuildRef {
+    Number(JobBuild),
+    Permalink(Job, Permalink),
+}
+
+impl BuildRef {
+    // Job urls without a build number or permalink refer to the `default`
+    // permalink of the job
+    pub fn new(url: &Url, default: Permalink) -> Result<BuildRef> {
+        if let Ok(build) = JobBuild::new(url) {
+            return Ok(BuildRef::Number(build));
+        }
+        let job = Job::new(url)?;
+        let permalink = Permalink::from_url(url)?.unwrap_or(default);
+        Ok(BuildRef::Permalink(job, permalink))
+    }
+
+    pub fn job(&self) -> &Job {
+        match self {
+            BuildRef::Number(build) => build.job(),
+            BuildRef::Permalink(job, _) => job,
+        }
+    }
+
+    pub fn url(&self) -> Result<Url> {
+        match self {
+            BuildRef::Number(build) => build.url(),
+            BuildRef::Permalink(job, permalink) => Ok(job.url()?.join(&format!("{permalink}/"))?),
+        }
+    }
+
+    pub fn api_path(&self, tree: &str) -> Result<Url> {
+        let mut url = self.url()?.join("api/json")?;
+        url.query_pairs_mut().append_pair("tree", tree);
+        Ok(url)
+    }
+}
+
+impl Display for BuildRef {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BuildRef::Number(build) => write!(f, "{build}"),
+            BuildRef::Permalink(job, permalink) => write!(f, "{job} {permalink}"),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -164,8 +315,8 @@ mod tests {
         let u = Url::parse("http://jenkins.invalid/job/")?;
 
         let params: Params = [
-            ("a".to_owned(), "1".to_owned()),
-            ("b".to_owned(), "2".to_owned()),
+            ("a".to_owned(), vec!["1".to_owned()]),
+            ("b".to_owned(), vec!["2".to_owned()]),
         ]
         .iter()
         .cloned()
@@ -188,6 +339,30 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_job_url_escapes() -> Result<()> {
+        let u = Url::parse("http://jenkins.invalid/job/")?;
+
+        let job = Job::new(&u.join("mb/job/feature%252Ffoo/")?)?;
+        assert_eq!(format!("{job}"), "mb » feature%2Ffoo");
+        assert_eq!(
+            job.url()?.as_str(),
+            "http://jenkins.invalid/job/mb/job/feature%252Ffoo/"
+        );
+        assert_eq!(
+            job.build(3).log_path(0)?.as_str(),
+            "http://jenkins.invalid/job/mb/job/feature%252Ffoo/3/logText/progressiveText?start=0"
+        );
+
+        let job = Job::new(&u.join("my%20job/")?)?.child("feature%2Ffoo");
+        assert_eq!(
+            job.url()?.as_str(),
+            "http://jenkins.invalid/job/my%20job/job/feature%252Ffoo/"
+        );
+
+        Ok(())
+    }
+
     #[test]
     fn test_blue_job_display() -> Result<()> {
         let u = Url::parse("http://jenkins.invalid/blue/organizations/jenkins/")?;
@@ -251,6 +426,57 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_build_ref() -> Result<()> {
+        let u = Url::parse("http://jenkins.invalid/job/")?;
+
+        let b = BuildRef::new(&u.join("a/job/b/2/")?, Permalink::LastBuild)?;
+        assert_eq!(format!("{b}"), "a » b #2");
+        assert_eq!(
+            b.url()?,
+            Url::parse("http://jenkins.invalid/job/a/job/b/2/")?
+        );
+
+        let b = BuildRef::new(&u.join("a/job/b/")?, Permalink::LastBuild)?;
+        assert_eq!(format!("{b}"), "a » b lastBuild");
+        assert_eq!(
+            b.url()?,
+            Url::parse("http://jenkins.invalid/job/a/job/b/lastBuild/")?
+        );
+
+        let b = BuildRef::new(
+            &u.join("a/job/b/lastSuccessfulBuild")?,
+            Permalink::LastBuild,
+        )?;
+        assert_eq!(format!("{b}"), "a » b lastSuccessfulBuild");
+        assert_eq!(
+            b.api_path("number")?,
+            Url::parse(
+                "http://jenkins.invalid/job/a/job/b/lastSuccessfulBuild/api/json?tree=number"
+            )?
+        );
+
+        for page in ["console", "consoleFull", "api/json"] {
+            let b = BuildRef::new(
+                &u.join(&format!("a/job/b/lastFailedBuild/{page}"))?,
+                Permalink::LastBuild,
+            )?;
+            assert_eq!(format!("{b}"), "a » b lastFailedBuild");
+        }
+
+        let err = BuildRef::new(&u.join("a/job/b/lastFailed/console")?, Permalink::LastBuild)
+            .unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("lastFailed is not a build number or permalink"));
+
+        let u = Url::parse("http://jenkins.invalid/blue/organizations/jenkins/")?;
+        let b = BuildRef::new(&u.join("a%2Fb/activity")?, Permalink::LastFailedBuild)?;
+        assert_eq!(format!("{b}"), "a » b lastFailedBuild");
+
+        Ok(())
+    }
+
     #[test]
     fn test_log_path() -> Result<()> {
         let u = Url::parse("http://jenkins.invalid/blue/organizations/jenkins/")?;
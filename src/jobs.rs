@@ -0,0 +1,130 @@
+use std::io::IsTerminal;
+
+use anyhow::Result;
+use glob::Pattern;
+use serde::{de::IgnoredAny, Deserialize, Serialize};
+use url::Url;
+
+use crate::{client::JenkinsClient, job::Job, output::Format};
+
+// Folders report their children's names, which is all we need to know
+// that they can be walked into
+const JOBS_TREE: &str = "jobs[name,url,color,jobs[name]]";
+
+#[derive(Deserialize, Debug)]
+struct Folder {
+    #[serde(default)]
+    jobs: Vec<Item>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Item {
+    name: String,
+    url: String,
+    color: Option<String>,
+    jobs: Option<Vec<IgnoredAny>>,
+}
+
+// A job or folder, as printed in the structured output formats
+#[derive(Serialize, Debug)]
+pub struct JobSummary {
+    pub name: String,
+    // The display path, as in `folder » job`
+    pub path: String,
+    pub url: String,
+    // The Jenkins ball color, null for folders
+    pub color: Option<String>,
+    pub folder: bool,
+}
+
+enum Step {
+    Walk(Url, usize),
+    Show(Item, usize),
+}
+
+// Jenkins encodes the status of the last build as a ball color, with an
+// `_anime` suffix while a build is running
+fn status(color: &Option<String>, is_folder: bool) -> (String, &'static str) {
+    let Some(color) = color else {
+        return (if is_folder { "folder" } else { "-" }.to_owned(), "");
+    };
+    let (base, running) = match color.strip_suffix("_anime") {
+        Some(base) => (base, true),
+        None => (color.as_str(), false),
+    };
+    let (word, ansi) = match base {
+        "blue" => ("success", "\x1b[32m"),
+        "red" => ("failure", "\x1b[31m"),
+        "yellow" => ("unstable", "\x1b[33m"),
+        "aborted" => ("aborted", "\x1b[90m"),
+        "notbuilt" => ("notbuilt", "\x1b[90m"),
+        "disabled" => ("disabled", "\x1b[90m"),
+        other => (other, ""),
+    };
+    if running {
+        (format!("{}*", word), ansi)
+    } else {
+        (word.to_owned(), ansi)
+    }
+}
+
+// Lists the jobs in a folder, or in the whole server if no folder is given,
+// walking into subfolders up to `max_depth` levels (unlimited if None)
+pub async fn jobs(
+    folder: Option<String>,
+    max_depth: Option<usize>,
+    name: Option<String>,
+    format: Format,
+    client: &JenkinsClient,
+) -> Result<()> {
+    let root = match folder {
+        Some(f) => Job::new(&client.resolve(&f)?)?.url()?,
+        None => client.base_url().clone(),
+    };
+    let pattern = name.as_deref().map(Pattern::new).transpose()?;
+
+    // Items are shown before walking into them, so folders come right
+    // before their contents
+    let mut shown = vec![];
+    let mut stack = vec![Step::Walk(root, 1)];
+    while let Some(step) = stack.pop() {
+        match step {
+            Step::Walk(url, depth) => {
+                let mut api = url.join("api/json")?;
+                api.query_pairs_mut().append_pair("tree", JOBS_TREE);
+                let folder: Folder = client.get_json(api).await?;
+                stack.extend(folder.jobs.into_iter().rev().map(|i| Step::Show(i, depth)));
+            }
+            Step::Show(item, depth) => {
+                let url = Url::parse(&item.url)?;
+                let is_folder = item.jobs.is_some();
+
+                if pattern.as_ref().is_none_or(|p| p.matches(&item.name)) {
+                    shown.push(JobSummary {
+                        path: Job::new(&url)?.to_string(),
+                        name: item.name,
+                        url: item.url,
+                        color: item.color,
+                        folder: is_folder,
+                    });
+                }
+
+                if is_folder && max_depth.is_none_or(|d| depth < d) {
+                    stack.push(Step::Walk(url, depth + 1));
+                }
+            }
+        }
+    }
+
+    let color = std::io::stdout().is_terminal();
+    format.print(&shown, |shown| {
+        for job in shown {
+            let (word, ansi) = status(&job.color, job.folder);
+            if color && !ansi.is_empty() {
+                println!("{:<10} {}{}\x1b[0m", word, ansi, job.path);
+            } else {
+                println!("{:<10} {}", word, job.path);
+            }
+        }
+    })
+}
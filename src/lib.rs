@@ -4,3 +4,15 @@ pub mod cli;
 pub mod run;
 pub mod job;
 pub mod params;
+pub mod client;
+pub mod status;
+pub mod queue;
+pub mod stop;
+pub mod jobs;
+pub mod builds;
+pub mod output;
+pub mod rebuild;
+pub mod params_file;
+pub mod downstream;
+pub mod stages;
+pub mod input;
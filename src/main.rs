@@ -1,16 +1,37 @@
-use std::collections::HashMap;
+use std::process::ExitCode;
 
 use anyhow::Result;
 use clap::Parser;
 
-use jenkins_cli::cli::JenkinsArgs;
+use jenkins_cli::builds::{builds, BuildFilter};
+use jenkins_cli::cli::{JenkinsArgs, WaitArgs};
+use jenkins_cli::client::JenkinsClient;
 use jenkins_cli::config::JenkinsConfig;
-use jenkins_cli::run::run;
-use jenkins_cli::tail::tail;
-use jenkins_cli::params::params;
+use jenkins_cli::input::input;
+use jenkins_cli::job::Params;
+use jenkins_cli::jobs::jobs;
+use jenkins_cli::output::Format;
+use jenkins_cli::params::{collect_params, definitions, params};
+use jenkins_cli::params_file::read_params_file;
+use jenkins_cli::rebuild::rebuild;
+use jenkins_cli::run::{run, RunOptions};
+use jenkins_cli::stages::stages;
+use jenkins_cli::status::status;
+use jenkins_cli::stop::{stop, StopSignal};
+use jenkins_cli::tail::{tail, TailOptions};
+
+fn run_options(wait: &WaitArgs, force: bool, format: Format) -> RunOptions {
+    RunOptions {
+        wait: !wait.no_wait,
+        queue_timeout: wait.queue_timeout,
+        abort_on_interrupt: wait.abort_on_interrupt,
+        force,
+        format,
+    }
+}
 
 #[tokio::main()]
-async fn main() -> Result<()> {
+async fn main() -> Result<ExitCode> {
     let args = JenkinsArgs::parse();
 
     if args.show_config_path {
@@ -20,14 +41,14 @@ async fn main() -> Result<()> {
                 .to_str()
                 .unwrap_or("")
         );
-        return Ok(());
+        return Ok(ExitCode::SUCCESS);
     }
 
     let mut config = JenkinsConfig::new(&args.config_path)?;
 
     if args.show_config {
         println!("{:?}", config);
-        return Ok(());
+        return Ok(ExitCode::SUCCESS);
     }
 
     if let Some(p) = args.profile {
@@ -35,20 +56,129 @@ async fn main() -> Result<()> {
     }
 
     if let Some(action) = args.action {
-        match action {
+        let client = JenkinsClient::new(config.profile()?)?;
+        let format = args.output;
+        let code = match action {
             jenkins_cli::cli::Action::Run(run_args) => {
-                let params = HashMap::from_iter(run_args.params);
-                run(&run_args.job_name, &params, config).await?
+                let mut params = Params::new();
+                if let Some(file) = &run_args.params_file {
+                    params = collect_params(read_params_file(file)?);
+                }
+                params.extend(collect_params(run_args.params));
+                let options = run_options(&run_args.wait, run_args.force, format);
+                run(&run_args.job_name, &params, &options, &client).await?
+            }
+            jenkins_cli::cli::Action::Rebuild(rebuild_args) => {
+                let options = run_options(&rebuild_args.wait, rebuild_args.force, format);
+                rebuild(
+                    rebuild_args.build_url,
+                    rebuild_args.params,
+                    &options,
+                    &client,
+                )
+                .await?
             }
             jenkins_cli::cli::Action::Tail(tail_args) => {
-                tail(tail_args.job_url, config).await?
+                let options = TailOptions {
+                    follow_job: tail_args.follow_job,
+                    lines: tail_args.lines,
+                    start_byte: tail_args.start_byte,
+                    no_follow: tail_args.no_follow,
+                    prefix: tail_args.prefix,
+                    tree: tail_args.tree,
+                    group: tail_args.group,
+                    max_concurrent_tails: tail_args.max_concurrent_tails,
+                    max_depth: if tail_args.no_subjobs {
+                        Some(0)
+                    } else {
+                        tail_args.depth
+                    },
+                    stage: tail_args.stage,
+                    by_stage: tail_args.by_stage,
+                };
+                tail(tail_args.job_url, &options, format, &client).await?;
+                ExitCode::SUCCESS
             }
             jenkins_cli::cli::Action::Params(params_args) => {
-                params(params_args.job_url, config).await?
+                if params_args.definitions {
+                    definitions(params_args.job_url, format, &client).await?;
+                } else {
+                    params(params_args.job_url, format, &client).await?;
+                }
+                ExitCode::SUCCESS
+            }
+            jenkins_cli::cli::Action::Status(status_args) => {
+                status(status_args.job_url, status_args.build, format, &client).await?
+            }
+            jenkins_cli::cli::Action::Stop(stop_args) => {
+                let mut signals = vec![StopSignal::Stop];
+                if stop_args.term || stop_args.kill {
+                    signals.push(StopSignal::Term);
+                }
+                if stop_args.kill {
+                    signals.push(StopSignal::Kill);
+                }
+                stop(
+                    stop_args.job_url,
+                    &signals,
+                    stop_args.grace,
+                    format,
+                    &client,
+                )
+                .await?;
+                ExitCode::SUCCESS
+            }
+            jenkins_cli::cli::Action::Jobs(jobs_args) => {
+                let max_depth = match (jobs_args.depth, jobs_args.recursive) {
+                    (Some(depth), _) => Some(depth),
+                    (None, true) => None,
+                    (None, false) => Some(1),
+                };
+                jobs(
+                    jobs_args.folder_url,
+                    max_depth,
+                    jobs_args.name,
+                    format,
+                    &client,
+                )
+                .await?;
+                ExitCode::SUCCESS
+            }
+            jenkins_cli::cli::Action::Builds(builds_args) => {
+                let filter = BuildFilter {
+                    results: builds_args.result,
+                    since: builds_args.since,
+                    branch: builds_args.branch,
+                    limit: builds_args.limit,
+                };
+                builds(builds_args.job_url, &filter, format, &client).await?;
+                ExitCode::SUCCESS
+            }
+            jenkins_cli::cli::Action::Stages(stages_args) => {
+                stages(
+                    stages_args.job_url,
+                    stages_args.failed_logs,
+                    format,
+                    &client,
+                )
+                .await?;
+                ExitCode::SUCCESS
+            }
+            jenkins_cli::cli::Action::Input(input_args) => {
+                input(
+                    input_args.build_url,
+                    input_args.decision,
+                    input_args.id,
+                    input_args.params,
+                    format,
+                    &client,
+                )
+                .await?;
+                ExitCode::SUCCESS
             }
-        }
-        return Ok(());
+        };
+        return Ok(code);
     }
 
-    Ok(())
+    Ok(ExitCode::SUCCESS)
 }
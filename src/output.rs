@@ -0,0 +1,48 @@
+use std::fmt::Display;
+
+use anyhow::Result;
+use clap::ValueEnum;
+use serde::Serialize;
+
+// How commands print their results. The structured formats are meant for
+// scripts, so the serialized structs must stay backwards compatible.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    #[default]
+    Text,
+    Json,
+    Yaml,
+}
+
+impl Format {
+    // Prints the result of a command. Text output is left to `text`, since
+    // it's meant for humans and doesn't need to show every field.
+    pub fn print<T: Serialize>(&self, value: &T, text: impl FnOnce(&T)) -> Result<()> {
+        match self {
+            Format::Text => text(value),
+            Format::Json => println!("{}", serde_json::to_string_pretty(value)?),
+            Format::Yaml => print!("{}", serde_yaml::to_string(value)?),
+        }
+        Ok(())
+    }
+
+    // Prints one of a stream of records, like log lines: a compact JSON
+    // object per line, or a YAML document each
+    pub fn print_record<T: Serialize>(&self, value: &T, text: impl FnOnce(&T)) -> Result<()> {
+        match self {
+            Format::Text => text(value),
+            Format::Json => println!("{}", serde_json::to_string(value)?),
+            Format::Yaml => print!("---\n{}", serde_yaml::to_string(value)?),
+        }
+        Ok(())
+    }
+
+    // Progress messages go to stderr in the structured formats, to keep
+    // stdout parseable
+    pub fn progress(&self, msg: impl Display) {
+        match self {
+            Format::Text => println!("{}", msg),
+            Format::Json | Format::Yaml => eprintln!("{}", msg),
+        }
+    }
+}
@@ -1,10 +1,17 @@
-use anyhow::Result;
-use reqwest::Client;
-use reqwest::Url;
-use serde::Deserialize;
+use std::fmt::Display;
+
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
-use crate::{config::JenkinsConfig, job::JobBuild};
+use crate::{
+    client::JenkinsClient,
+    job::{Job, JobBuild, Params},
+    output::Format,
+};
+
+const DEFINITIONS_TREE: &str = "property[parameterDefinitions[name,type,description,choices,\
+    defaultParameterValue[value],value,multiSelectDelimiter]]";
 
 #[derive(Debug, Deserialize)]
 struct WorkflowRun {
@@ -19,40 +26,523 @@ struct Action {
     parameters: Vec<ParameterValue>,
 }
 
+// A parameter value of a build. `_class` tells the parameter type, as in
+// hudson.model.StringParameterValue.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ParameterValue {
+    pub _class: String,
+    pub name: String,
+    #[serde(default)]
+    pub value: Value,
+}
+
+impl ParameterValue {
+    // The value as sent in a form when triggering a build. Parameters
+    // without a value, like passwords, have none.
+    pub fn form_value(&self) -> Option<String> {
+        match &self.value {
+            Value::Null => None,
+            Value::String(s) => Some(s.to_owned()),
+            other => Some(other.to_string()),
+        }
+    }
+}
+
 #[derive(Debug, Deserialize)]
-struct ParameterValue {
-    _class: String,
-    name: String,
+struct JobProperties {
+    #[serde(default)]
+    property: Vec<Property>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Property {
+    #[serde(default)]
+    parameter_definitions: Vec<ParameterDefinition>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ParameterKind {
+    String,
+    Boolean,
+    Choice,
+    Password,
+    Text,
+    File,
+    ExtendedChoice,
+    Other,
+}
+
+impl Display for ParameterKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            ParameterKind::String => "string",
+            ParameterKind::Boolean => "boolean",
+            ParameterKind::Choice => "choice",
+            ParameterKind::Password => "password",
+            ParameterKind::Text => "text",
+            ParameterKind::File => "file",
+            ParameterKind::ExtendedChoice => "extended-choice",
+            ParameterKind::Other => "other",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+impl ParameterKind {
+    // Plugins define their own parameter types, so we go by class name,
+    // which is more reliable than the `type` they report
+    pub(crate) fn from_class(class: &str) -> ParameterKind {
+        match class.rsplit('.').next().unwrap_or_default() {
+            "StringParameterDefinition" => ParameterKind::String,
+            "BooleanParameterDefinition" => ParameterKind::Boolean,
+            "ChoiceParameterDefinition" => ParameterKind::Choice,
+            "PasswordParameterDefinition" => ParameterKind::Password,
+            "TextParameterDefinition" => ParameterKind::Text,
+            "FileParameterDefinition" => ParameterKind::File,
+            "ExtendedChoiceParameterDefinition" => ParameterKind::ExtendedChoice,
+            _ => ParameterKind::Other,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct DefaultValue {
+    #[serde(default)]
     value: Value,
 }
 
-pub async fn params(job: String, config: JenkinsConfig) -> Result<()> {
-    let client = Client::new();
+// A parameter a job takes, from its ParametersDefinitionProperty
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ParameterDefinition {
+    #[serde(rename = "_class")]
+    pub _class: String,
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    choices: Vec<String>,
+    #[serde(default)]
+    default_parameter_value: Option<DefaultValue>,
+    // The choices of extended choice parameters, joined by commas
+    #[serde(default)]
+    value: Option<String>,
+    // How extended choice parameters are shown, as in PT_CHECKBOX
+    #[serde(default, rename = "type")]
+    choice_type: Option<String>,
+    #[serde(default)]
+    multi_select_delimiter: Option<String>,
+}
+
+// A parameter definition, as printed in the structured output formats
+#[derive(Debug, Serialize)]
+pub struct DefinitionSummary {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub kind: ParameterKind,
+    pub _class: String,
+    // null if the job has no default, or hides it, as for passwords
+    pub default: Option<Value>,
+    // empty unless the type is choice or extended-choice
+    pub choices: Vec<String>,
+    pub description: Option<String>,
+}
+
+impl ParameterDefinition {
+    pub fn kind(&self) -> ParameterKind {
+        ParameterKind::from_class(&self._class)
+    }
+
+    pub fn choices(&self) -> Vec<String> {
+        match self.kind() {
+            ParameterKind::ExtendedChoice => self
+                .value
+                .as_deref()
+                .unwrap_or_default()
+                .split(',')
+                .filter(|c| !c.is_empty())
+                .map(str::to_owned)
+                .collect(),
+            _ => self.choices.clone(),
+        }
+    }
+
+    pub fn summary(&self) -> DefinitionSummary {
+        DefinitionSummary {
+            name: self.name.clone(),
+            kind: self.kind(),
+            _class: self._class.clone(),
+            default: self.default_value().cloned(),
+            choices: self.choices(),
+            description: self.description.clone().filter(|d| !d.is_empty()),
+        }
+    }
+
+    // Whether the parameter takes several values, which Jenkins expects
+    // joined by its delimiter
+    pub fn is_multi(&self) -> bool {
+        self.kind() == ParameterKind::ExtendedChoice
+            && matches!(
+                self.choice_type.as_deref(),
+                None | Some("PT_MULTI_SELECT" | "PT_CHECKBOX" | "PT_MULTI_LEVEL_MULTI_SELECT")
+            )
+    }
+
+    pub fn default_value(&self) -> Option<&Value> {
+        self.default_parameter_value
+            .as_ref()
+            .map(|d| &d.value)
+            .filter(|v| !v.is_null())
+    }
+}
+
+pub async fn job_parameters(job: &Job, client: &JenkinsClient) -> Result<Vec<ParameterDefinition>> {
+    let mut url = job.url()?.join("api/json")?;
+    url.query_pairs_mut().append_pair("tree", DEFINITIONS_TREE);
+    let job: JobProperties = client.get_json(url).await?;
+    Ok(job
+        .property
+        .into_iter()
+        .flat_map(|p| p.parameter_definitions)
+        .collect())
+}
+
+// Groups parameters given as NAME=VALUE, keeping every value of the ones
+// given more than once
+pub fn collect_params(pairs: impl IntoIterator<Item = (String, String)>) -> Params {
+    let mut params = Params::new();
+    for (name, value) in pairs {
+        params.entry(name).or_default().push(value);
+    }
+    params
+}
+
+// A parameter value as given on the command line. Values starting with `@`
+// are files to upload, and `@@` escapes the `@` of any other value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParamValue<'a> {
+    File(&'a str),
+    Text(&'a str),
+}
 
-    let url = if job.starts_with('/') {
-        config.profile()?.url()?.join(&job)?
+impl<'a> ParamValue<'a> {
+    pub fn parse(value: &'a str) -> ParamValue<'a> {
+        match value.strip_prefix('@') {
+            Some(rest) if rest.starts_with('@') => ParamValue::Text(rest),
+            Some(path) => ParamValue::File(path),
+            None => ParamValue::Text(value),
+        }
+    }
+}
+
+// Escapes a value so it's sent as text even if it starts with `@`, as the
+// values of a previous build
+pub fn escape_value(value: &str) -> String {
+    if value.starts_with('@') {
+        format!("@{}", value)
     } else {
-        Url::parse(&job)?
-    };
+        value.to_owned()
+    }
+}
 
-    let job = JobBuild::new(&url)?;
-    let url = job.params_path()?;
-    let profile = config.profile()?;
-    let resp = client
-        .get(url)
-        .basic_auth(&profile.username, Some(&profile.password))
-        .send()
-        .await?;
-
-    let run: WorkflowRun = resp.json().await?;
-    for action in run.actions {
-        if action._class != "hudson.model.ParametersAction" {
+// Joins the values of multi-select parameters given more than once, and
+// keeps the last value of the others, returning a warning for each
+pub fn join_values(definitions: &[ParameterDefinition], params: &mut Params) -> Vec<String> {
+    let mut warnings = vec![];
+    for (name, values) in params.iter_mut() {
+        if values.len() < 2 {
+            continue;
+        }
+        let Some(definition) = definitions.iter().find(|d| &d.name == name) else {
+            continue;
+        };
+        if definition.is_multi() {
+            let delimiter = definition.multi_select_delimiter.as_deref().unwrap_or(",");
+            let text: Vec<_> = values
+                .iter()
+                .map(|v| match ParamValue::parse(v) {
+                    ParamValue::File(_) => v.as_str(),
+                    ParamValue::Text(text) => text,
+                })
+                .collect();
+            *values = vec![escape_value(&text.join(delimiter))];
+        } else if definition.kind() != ParameterKind::Other {
+            warnings.push(format!(
+                "{} takes a single value, using the last one given, {:?}",
+                name,
+                values[values.len() - 1]
+            ));
+            values.drain(..values.len() - 1);
+        }
+    }
+    warnings.sort();
+    warnings
+}
+
+// Checks parameters given on the command line against the job definitions,
+// returning the definitions of the ones left to their defaults
+pub fn validate<'a>(
+    definitions: &'a [ParameterDefinition],
+    params: &Params,
+) -> Result<Vec<&'a ParameterDefinition>> {
+    let mut errors = vec![];
+
+    let mut names: Vec<_> = params.keys().collect();
+    names.sort();
+    for (name, value) in names
+        .into_iter()
+        .flat_map(|name| params[name].iter().map(move |value| (name, value)))
+    {
+        let Some(definition) = definitions.iter().find(|d| &d.name == name) else {
+            errors.push(format!("unknown parameter {}", name));
             continue;
+        };
+        let value = match ParamValue::parse(value) {
+            ParamValue::File(_) if definition.kind() != ParameterKind::File => {
+                errors.push(format!(
+                    "{} is not a file parameter, escape a leading @ as @@",
+                    name
+                ));
+                continue;
+            }
+            ParamValue::File(_) => continue,
+            ParamValue::Text(text) => text,
+        };
+        match definition.kind() {
+            ParameterKind::File => {
+                errors.push(format!(
+                    "{} is a file parameter, pass it as {}=@path",
+                    name, name
+                ));
+            }
+            ParameterKind::Boolean
+                if !value.eq_ignore_ascii_case("true") && !value.eq_ignore_ascii_case("false") =>
+            {
+                errors.push(format!("{} must be true or false, not {:?}", name, value));
+            }
+            ParameterKind::Choice if !definition.choices.iter().any(|c| c == value) => {
+                errors.push(format!(
+                    "{} must be one of {}, not {:?}",
+                    name,
+                    definition.choices().join(", "),
+                    value
+                ));
+            }
+            _ => {}
         }
-        for parameter in action.parameters {
+    }
+
+    errors.dedup();
+    if !errors.is_empty() {
+        let valid: Vec<_> = definitions.iter().map(|d| d.name.as_str()).collect();
+        bail!(
+            "Invalid parameters: {}. The job takes: {}. Use --force to skip this check.",
+            errors.join("; "),
+            if valid.is_empty() {
+                "no parameters".to_owned()
+            } else {
+                valid.join(", ")
+            }
+        );
+    }
+
+    Ok(definitions
+        .iter()
+        .filter(|d| !params.contains_key(&d.name))
+        .collect())
+}
+
+pub async fn build_parameters(
+    build: &JobBuild,
+    client: &JenkinsClient,
+) -> Result<Vec<ParameterValue>> {
+    let run: WorkflowRun = client.get_json(build.params_path()?).await?;
+    Ok(run
+        .actions
+        .into_iter()
+        .filter(|a| a._class == "hudson.model.ParametersAction")
+        .flat_map(|a| a.parameters)
+        .collect())
+}
+
+pub async fn params(job: String, format: Format, client: &JenkinsClient) -> Result<()> {
+    let url = client.resolve(&job)?;
+
+    let job = JobBuild::new(&url)?;
+    let parameters = build_parameters(&job, client).await?;
+
+    format.print(&parameters, |parameters| {
+        for parameter in parameters {
             println!("{}={}", parameter.name, parameter.value);
         }
+    })
+}
+
+pub async fn definitions(job: String, format: Format, client: &JenkinsClient) -> Result<()> {
+    let job = Job::new(&client.resolve(&job)?)?;
+    let definitions: Vec<_> = job_parameters(&job, client)
+        .await?
+        .iter()
+        .map(ParameterDefinition::summary)
+        .collect();
+
+    format.print(&definitions, |definitions| {
+        for definition in definitions {
+            match &definition.default {
+                Some(default) => {
+                    println!("{} ({}) = {}", definition.name, definition.kind, default)
+                }
+                None => println!("{} ({})", definition.name, definition.kind),
+            }
+            if !definition.choices.is_empty() {
+                println!("  choices: {}", definition.choices.join(", "));
+            }
+            if let Some(description) = &definition.description {
+                for line in description.lines() {
+                    println!("  {}", line);
+                }
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn definitions() -> Vec<ParameterDefinition> {
+        serde_json::from_value(serde_json::json!([
+            {
+                "_class": "hudson.model.StringParameterDefinition",
+                "name": "NAME",
+                "defaultParameterValue": { "value": "world" },
+            },
+            {
+                "_class": "hudson.model.BooleanParameterDefinition",
+                "name": "DRY_RUN",
+                "defaultParameterValue": { "value": true },
+            },
+            {
+                "_class": "hudson.model.ChoiceParameterDefinition",
+                "name": "ENV",
+                "choices": ["dev", "prod"],
+                "defaultParameterValue": { "value": "dev" },
+            },
+            {
+                "_class": "hudson.model.FileParameterDefinition",
+                "name": "ARCHIVE",
+            },
+        ]))
+        .unwrap()
+    }
+
+    fn params(params: &[(&str, &str)]) -> Params {
+        collect_params(params.iter().map(|(k, v)| (k.to_string(), v.to_string())))
+    }
+
+    #[test]
+    fn test_validate_defaults() -> Result<()> {
+        let definitions = definitions();
+
+        let defaults = validate(
+            &definitions,
+            &params(&[("ENV", "prod"), ("DRY_RUN", "False"), ("ARCHIVE", "@a.zip")]),
+        )?;
+        let names: Vec<_> = defaults.iter().map(|d| d.name.as_str()).collect();
+        assert_eq!(names, vec!["NAME"]);
+        assert_eq!(defaults[0].default_value(), Some(&Value::from("world")));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate_errors() {
+        let definitions = definitions();
+
+        let err = validate(&definitions, &params(&[("NAEM", "x")])).unwrap_err();
+        assert!(err.to_string().contains("unknown parameter NAEM"));
+        assert!(err
+            .to_string()
+            .contains("The job takes: NAME, DRY_RUN, ENV, ARCHIVE"));
+
+        let err = validate(&definitions, &params(&[("DRY_RUN", "yes")])).unwrap_err();
+        assert!(err.to_string().contains("DRY_RUN must be true or false"));
+
+        let err = validate(&definitions, &params(&[("ENV", "staging")])).unwrap_err();
+        assert!(err.to_string().contains("ENV must be one of dev, prod"));
+
+        let err = validate(&definitions, &params(&[("NAME", "@name.txt")])).unwrap_err();
+        assert!(err.to_string().contains("NAME is not a file parameter"));
+
+        validate(&definitions, &params(&[("NAME", "@@oncall")])).unwrap();
+
+        let err = validate(&definitions, &params(&[("ARCHIVE", "a.zip")])).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("ARCHIVE is a file parameter, pass it as ARCHIVE=@path"));
+
+        let err = validate(&[], &params(&[("A", "1")])).unwrap_err();
+        assert!(err.to_string().contains("The job takes: no parameters"));
+
+        let err = validate(&definitions, &params(&[("ENV", "dev"), ("ENV", "qa")])).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("ENV must be one of dev, prod, not \"qa\""));
     }
 
-    Ok(())
+    #[test]
+    fn test_join_values() -> Result<()> {
+        let definitions: Vec<ParameterDefinition> = serde_json::from_value(serde_json::json!([
+            {
+                "_class": "com.cwctravel.hudson.plugins.extended_choice_parameter.ExtendedChoiceParameterDefinition",
+                "name": "TARGETS",
+                "type": "PT_CHECKBOX",
+                "value": "linux,mac,windows",
+                "multiSelectDelimiter": ";",
+            },
+            {
+                "_class": "hudson.model.StringParameterDefinition",
+                "name": "NAME",
+            },
+            {
+                "_class": "org.biouno.unochoice.ChoiceParameter",
+                "name": "REGIONS",
+            },
+        ]))?;
+
+        let mut given = params(&[
+            ("TARGETS", "linux"),
+            ("TARGETS", "mac"),
+            ("NAME", "a"),
+            ("NAME", "b"),
+            ("REGIONS", "eu"),
+            ("REGIONS", "us"),
+        ]);
+        let warnings = join_values(&definitions, &mut given);
+
+        assert_eq!(given["TARGETS"], vec!["linux;mac"]);
+        assert_eq!(given["NAME"], vec!["b"]);
+        assert_eq!(given["REGIONS"], vec!["eu", "us"]);
+        assert_eq!(
+            warnings,
+            vec!["NAME takes a single value, using the last one given, \"b\""]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_param_value() {
+        assert_eq!(ParamValue::parse("@a.zip"), ParamValue::File("a.zip"));
+        assert_eq!(ParamValue::parse("@@oncall"), ParamValue::Text("@oncall"));
+        assert_eq!(ParamValue::parse("a@b"), ParamValue::Text("a@b"));
+        assert_eq!(escape_value("@oncall"), "@@oncall");
+        assert_eq!(
+            ParamValue::parse(&escape_value("@oncall")),
+            ParamValue::Text("@oncall")
+        );
+        assert_eq!(escape_value("plain"), "plain");
+    }
 }
@@ -0,0 +1,207 @@
+use std::{io::Read, path::Path};
+
+use anyhow::{anyhow, bail, Result};
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde_json::Value;
+
+use crate::params::escape_value;
+
+lazy_static! {
+    static ref ENV_LINE: Regex =
+        Regex::new(r"^\s*(?:export\s+)?([A-Za-z_][A-Za-z0-9_.-]*)\s*=\s*(.*)$").unwrap();
+}
+
+// Reads parameters from a TOML, YAML, JSON or .env file, or from stdin if
+// the path is `-`. The format is taken from the extension, and guessed
+// when there's none.
+pub fn read_params_file(path: &str) -> Result<Vec<(String, String)>> {
+    let contents = if path == "-" {
+        let mut contents = String::new();
+        std::io::stdin().read_to_string(&mut contents)?;
+        contents
+    } else {
+        std::fs::read_to_string(path)
+            .map_err(|e| anyhow!("Failed to read params file {}: {}", path, e))?
+    };
+
+    let extension = Path::new(path)
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or_default();
+    let result = match extension {
+        "json" => serde_json::from_str(&contents)
+            .map_err(anyhow::Error::from)
+            .and_then(from_value),
+        "yaml" | "yml" => serde_yaml::from_str(&contents)
+            .map_err(anyhow::Error::from)
+            .and_then(from_value),
+        "toml" => toml::from_str(&contents)
+            .map_err(anyhow::Error::from)
+            .and_then(from_value),
+        "env" => from_env(&contents),
+        _ => parse_guessing(&contents),
+    };
+    result.map_err(|e| anyhow!("Failed to parse params file {}: {}", path, e))
+}
+
+fn parse_guessing(contents: &str) -> Result<Vec<(String, String)>> {
+    if let Ok(value) = serde_json::from_str(contents) {
+        return from_value(value);
+    }
+    if let Ok(params) = from_env(contents) {
+        return Ok(params);
+    }
+    from_value(serde_yaml::from_str(contents)?)
+}
+
+// Lines of NAME=VALUE, as in .env files and in the text output of
+// `params`, which prints values as JSON
+fn from_env(contents: &str) -> Result<Vec<(String, String)>> {
+    let mut params = vec![];
+    for (n, line) in contents.lines().enumerate() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        let Some(captures) = ENV_LINE.captures(line) else {
+            bail!("line {} doesn't have the form NAME=VALUE", n + 1);
+        };
+        let name = &captures[1];
+        let raw = captures[2].trim();
+        // `params` prints hidden values, like passwords, as null
+        if raw == "null" {
+            eprintln!("Warning: {} is null, leaving it to its default", name);
+            continue;
+        }
+        // Only strings and lists are decoded, so that numbers like 1.10 are
+        // kept as written
+        let decoded = match raw.chars().next() {
+            Some('"' | '[') => serde_json::from_str::<Value>(raw).ok(),
+            _ => None,
+        };
+        let value = match decoded {
+            Some(value) => value,
+            None => match raw.strip_prefix('\'').and_then(|r| r.strip_suffix('\'')) {
+                Some(quoted) => Value::from(quoted),
+                None => Value::from(raw),
+            },
+        };
+        for value in values(value) {
+            params.push((name.to_owned(), value));
+        }
+    }
+    Ok(params)
+}
+
+// Either a map of names to values, or the list of `{name, value}` that
+// `params` prints in the structured formats
+fn from_value(value: Value) -> Result<Vec<(String, String)>> {
+    let mut params = vec![];
+    match value {
+        Value::Object(map) => {
+            for (name, value) in map {
+                for value in values(value) {
+                    params.push((name.clone(), value));
+                }
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                let Some(name) = item.get("name").and_then(Value::as_str) else {
+                    bail!("expected a list of {{name, value}}");
+                };
+                for value in values(item.get("value").cloned().unwrap_or_default()) {
+                    params.push((name.to_owned(), value));
+                }
+            }
+        }
+        _ => bail!("expected a map of parameters, or a list of {{name, value}}"),
+    }
+    Ok(params)
+}
+
+// Parameters without a value, like the passwords `params` hides, are left
+// to their defaults. Arrays give a value each, for multi-select parameters.
+// Values are always text, so a leading `@` is escaped rather than taken as
+// a file to upload.
+fn values(value: Value) -> Vec<String> {
+    match value {
+        Value::Null => vec![],
+        Value::String(s) => vec![escape_value(&s)],
+        Value::Array(items) => items.into_iter().flat_map(values).collect(),
+        other => vec![other.to_string()],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params(params: &[(&str, &str)]) -> Vec<(String, String)> {
+        params
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_from_env() -> Result<()> {
+        let contents = "# comment\n\
+            NAME=\"world\"\n\
+            DRY_RUN=true\n\
+            export ENV=prod\n\
+            QUOTED='a b'\n\
+            SECRET=null\n\
+            TARGETS=[\"linux\", \"mac\"]\n\
+            VERSION=1.10\n\
+            ID=12345678901234567890123\n\
+            NOTIFY=\"@oncall\"\n";
+        assert_eq!(
+            from_env(contents)?,
+            params(&[
+                ("NAME", "world"),
+                ("DRY_RUN", "true"),
+                ("ENV", "prod"),
+                ("QUOTED", "a b"),
+                ("TARGETS", "linux"),
+                ("TARGETS", "mac"),
+                ("VERSION", "1.10"),
+                ("ID", "12345678901234567890123"),
+                ("NOTIFY", "@@oncall"),
+            ])
+        );
+        assert!(from_env("NAME world").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_guessing() -> Result<()> {
+        let expected = params(&[("ENV", "prod"), ("NAME", "world")]);
+
+        assert_eq!(
+            parse_guessing(r#"{"ENV": "prod", "NAME": "world"}"#)?,
+            expected
+        );
+        assert_eq!(
+            parse_guessing(
+                r#"[
+                    {"_class": "hudson.model.StringParameterValue", "name": "ENV", "value": "prod"},
+                    {"_class": "hudson.model.StringParameterValue", "name": "NAME", "value": "world"}
+                ]"#
+            )?,
+            expected
+        );
+        assert_eq!(
+            parse_guessing("- name: ENV\n  value: prod\n- name: NAME\n  value: world\n")?,
+            expected
+        );
+        assert_eq!(
+            from_value(toml::from_str("ENV = \"prod\"\nNAME = \"world\"\n")?)?,
+            expected
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,156 @@
+use anyhow::{anyhow, bail, Result};
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::time::Instant;
+use url::Url;
+
+use crate::{client::JenkinsClient, output::Format};
+
+// Polling backs off up to this interval while the item is waiting
+const MAX_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(Deserialize, Debug)]
+struct QueueItem {
+    #[serde(default)]
+    blocked: bool,
+    #[serde(default)]
+    stuck: bool,
+    #[serde(default)]
+    cancelled: bool,
+    why: Option<String>,
+    task: Option<Task>,
+    executable: Option<Executable>,
+    timestamp: Option<u128>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Task {
+    pub name: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Executable {
+    pub number: u32,
+    pub url: String,
+}
+
+// A queue item, as printed in the structured output formats
+#[derive(Serialize, Debug)]
+pub struct QueueInfo {
+    pub id: u64,
+    pub url: String,
+}
+
+// An item in the Jenkins build queue, as returned in the Location header
+// when a build is triggered
+pub struct Queue<'a> {
+    url: Url,
+    id: u64,
+    format: Format,
+    client: &'a JenkinsClient,
+}
+
+impl<'a> Queue<'a> {
+    pub fn new(url: &Url, format: Format, client: &'a JenkinsClient) -> Result<Queue<'a>> {
+        let id = url
+            .path_segments()
+            .and_then(|mut s| s.rfind(|s| !s.is_empty()))
+            .and_then(|s| s.parse().ok())
+            .ok_or_else(|| anyhow!("Failed to parse queue item id from url: {}", url))?;
+        Ok(Queue {
+            url: url.clone(),
+            id,
+            format,
+            client,
+        })
+    }
+
+    pub fn info(&self) -> QueueInfo {
+        QueueInfo {
+            id: self.id,
+            url: self.url.to_string(),
+        }
+    }
+
+    // Waits until the item leaves the queue and becomes a build. The item is
+    // cancelled if the timeout expires or the user hits Ctrl-C, which gives
+    // None.
+    pub async fn wait(&self, timeout: Option<Duration>) -> Result<Option<(Task, Executable)>> {
+        let deadline = async {
+            match timeout {
+                Some(t) => tokio::time::sleep(t).await,
+                None => std::future::pending().await,
+            }
+        };
+
+        tokio::select! {
+            result = self.watch() => result.map(Some),
+            _ = deadline => {
+                self.cancel().await?;
+                bail!(
+                    "Queue item {} didn't start within {}, cancelled it",
+                    self.id,
+                    humantime::format_duration(timeout.unwrap_or_default())
+                );
+            }
+            _ = tokio::signal::ctrl_c() => {
+                self.cancel().await?;
+                eprintln!("Error: Interrupted, cancelled queue item {}", self.id);
+                Ok(None)
+            }
+        }
+    }
+
+    async fn watch(&self) -> Result<(Task, Executable)> {
+        self.format
+            .progress(format!("Waiting on queue item: {}...", self.url));
+        let api = self.url.join("api/json")?;
+        let mut interval = Duration::from_secs(1);
+        let mut last_why = None;
+
+        loop {
+            let item: QueueItem = self.client.get_json(api.clone()).await?;
+
+            if item.cancelled {
+                bail!("Queue item {} was cancelled", self.id);
+            }
+
+            if let (Some(task), Some(exec)) = (item.task, item.executable) {
+                return Ok((task, exec));
+            }
+
+            if item.why.is_some() && item.why != last_why {
+                let why = item.why.as_deref().unwrap_or_default();
+                if item.stuck {
+                    self.format.progress(format!("Stuck: {}", why));
+                } else if item.blocked {
+                    self.format.progress(format!("Blocked: {}", why));
+                } else {
+                    self.format.progress(why);
+                }
+                last_why = item.why;
+            }
+
+            // Items in their quiet period report when it ends
+            if let Some(ts) = item.timestamp {
+                let unix_now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis();
+                if ts > unix_now {
+                    let until = Instant::now() + Duration::from_millis((ts - unix_now) as u64);
+                    tokio::time::sleep_until(until).await;
+                    continue;
+                }
+            }
+
+            tokio::time::sleep(interval).await;
+            interval = (interval * 2).min(MAX_POLL_INTERVAL);
+        }
+    }
+
+    pub async fn cancel(&self) -> Result<()> {
+        let mut url = self.client.base_url().join("queue/cancelItem")?;
+        url.query_pairs_mut()
+            .append_pair("id", &self.id.to_string());
+        self.client.send(self.client.post(url).await?).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,39 @@
+use std::process::ExitCode;
+
+use anyhow::Result;
+
+use crate::{
+    client::JenkinsClient,
+    job::{JobBuild, Params},
+    params::{build_parameters, collect_params, escape_value},
+    run::{run, RunOptions},
+};
+
+// Triggers a new build of the same job with the parameters of an existing
+// build, replacing the ones given in `overrides`
+pub async fn rebuild(
+    build: String,
+    overrides: Vec<(String, String)>,
+    options: &RunOptions,
+    client: &JenkinsClient,
+) -> Result<ExitCode> {
+    let build = JobBuild::new(&client.resolve(&build)?)?;
+
+    let mut params = Params::new();
+    for parameter in build_parameters(&build, client).await? {
+        match parameter.form_value() {
+            // Old values are text, even if they start with @
+            Some(value) => {
+                params.insert(parameter.name, vec![escape_value(&value)]);
+            }
+            None => eprintln!(
+                "Warning: {} has no value to reuse, the job default will be used",
+                parameter.name
+            ),
+        }
+    }
+    params.extend(collect_params(overrides));
+
+    options.format.progress(format!("Rebuilding {}...", build));
+    run(&build.job().url()?, &params, options, client).await
+}
@@ -1,56 +1,129 @@
-use anyhow::{bail, Result};
-use reqwest::{Client, Response};
-use serde::Deserialize;
-use std::{
-    collections::HashMap,
-    time::{Duration, SystemTime, UNIX_EPOCH},
-};
+use anyhow::{anyhow, bail, Result};
+use reqwest::multipart::{Form, Part};
+use std::{path::Path, process::ExitCode, time::Duration};
 use url::Url;
 
-use crate::{config::JenkinsConfig, job::Job, tail::tail};
+use crate::{
+    client::{resp_error, JenkinsClient},
+    job::{BuildRef, Job, JobBuild, Params},
+    output::Format,
+    params::{job_parameters, join_values, validate, ParamValue, ParameterKind},
+    queue::Queue,
+    status::{BuildInfo, BuildResult},
+    stop::{signal, StopSignal},
+    tail::{tail, TailOptions},
+};
 
-#[derive(Deserialize, Debug)]
-struct QueueResponse {
-    why: Option<String>,
-    task: Option<Task>,
-    executable: Option<Executable>,
-    timestamp: Option<u128>,
-}
+// The exit code of shells for processes ended by SIGINT
+const INTERRUPTED: u8 = 130;
 
-#[derive(Deserialize, Debug)]
-struct Task {
-    name: String,
+pub struct RunOptions {
+    // Tail the build until it finishes, and exit with its result
+    pub wait: bool,
+    // Cancel the queue item if the build hasn't started after this long
+    pub queue_timeout: Option<Duration>,
+    // Abort the build if the user hits Ctrl-C while tailing it
+    pub abort_on_interrupt: bool,
+    // Trigger the build without checking the parameters against the job
+    pub force: bool,
+    pub format: Format,
 }
 
-#[derive(Deserialize, Debug)]
-struct Executable {
-    number: u32,
-    url: String,
+fn format_size(bytes: usize) -> String {
+    let units = ["B", "KiB", "MiB", "GiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < units.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", size, units[unit])
+    }
 }
 
-async fn resp_error(resp: Response, msg: &str) -> Result<String> {
-    Ok(format!(
-        "{}: status: {:?}, headers: {:?}, body: {:?}",
-        msg,
-        resp.status(),
-        resp.headers().clone(),
-        resp.text().await?
-    ))
+// Parameters given as NAME=@path are file parameters, which Jenkins only
+// takes in a multipart request, with the file as a part named after them
+async fn multipart(params: &[(&str, ParamValue<'_>)], format: Format) -> Result<Form> {
+    let mut form = Form::new();
+    for &(name, value) in params {
+        let path = match value {
+            ParamValue::File(path) => path,
+            ParamValue::Text(text) => {
+                form = form.text(name.to_owned(), text.to_owned());
+                continue;
+            }
+        };
+        let data = tokio::fs::read(path)
+            .await
+            .map_err(|e| anyhow!("Failed to read {} for {}: {}", path, name, e))?;
+        format.progress(format!(
+            "Uploading {} as {} ({})",
+            path,
+            name,
+            format_size(data.len())
+        ));
+        let file_name = Path::new(path)
+            .file_name()
+            .map(|f| f.to_string_lossy().into_owned())
+            .unwrap_or_else(|| name.to_owned());
+        form = form.part(name.to_owned(), Part::bytes(data).file_name(file_name));
+    }
+    Ok(form)
 }
 
-pub async fn run(job: &Url, params: &HashMap<String, String>, config: JenkinsConfig) -> Result<()> {
-    let client = Client::new();
-
+pub async fn run(
+    job: &Url,
+    params: &Params,
+    options: &RunOptions,
+    client: &JenkinsClient,
+) -> Result<ExitCode> {
     let job = Job::new(job)?;
-    let full_path = job.build_path(params);
-    let profile = config.profile()?;
-    let url = profile.url()?.join(&full_path)?;
-    let resp = client
-        .post(url)
-        .basic_auth(&profile.username, Some(&profile.password))
-        .form(params)
-        .send()
-        .await?;
+    let format = options.format;
+
+    let mut params = params.clone();
+    if !options.force {
+        let definitions = job_parameters(&job, client).await?;
+        for warning in join_values(&definitions, &mut params) {
+            eprintln!("Warning: {}", warning);
+        }
+        for definition in validate(&definitions, &params)? {
+            if definition.kind() == ParameterKind::Password {
+                continue;
+            }
+            if let Some(value) = definition.default_value() {
+                format.progress(format!("Using default {}={}", definition.name, value));
+            }
+        }
+    }
+
+    let full_path = job.build_path(&params);
+    let url = client.base_url().join(&full_path)?;
+    // Parameters given more than once are sent once per value
+    let pairs: Vec<_> = params
+        .iter()
+        .flat_map(|(name, values)| {
+            values
+                .iter()
+                .map(move |value| (name.as_str(), ParamValue::parse(value)))
+        })
+        .collect();
+    let req = client.post(url).await?;
+    let req = if pairs.iter().any(|(_, v)| matches!(v, ParamValue::File(_))) {
+        req.multipart(multipart(&pairs, format).await?)
+    } else {
+        let text: Vec<_> = pairs
+            .iter()
+            .filter_map(|&(name, value)| match value {
+                ParamValue::Text(text) => Some((name, text)),
+                ParamValue::File(_) => None,
+            })
+            .collect();
+        req.form(&text)
+    };
+    let resp = client.send(req).await?;
 
     if resp.status() != 201 {
         bail!(resp_error(resp, "Unexpected response").await?);
@@ -60,41 +133,50 @@ pub async fn run(job: &Url, params: &HashMap<String, String>, config: JenkinsCon
         bail!(resp_error(resp, "Location header missing in response").await?);
     }
 
-    for i in 1..10 {
-        let loc = resp.headers().get("location").unwrap().to_str()?;
-        println!("Waiting on queue item: {}...", loc);
-        let loc = Url::parse(loc)?.join("api/json")?;
-        let queue_resp: QueueResponse = client
-            .get(loc)
-            .basic_auth(&profile.username, Some(&profile.password))
-            .send()
-            .await?
-            .json()
-            .await?;
-
-        if let Some(why) = queue_resp.why {
-            println!("{}", why);
-            if let Some(ts) = queue_resp.timestamp {
-                let unix_now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis();
-                if ts > unix_now {
-                    let until =
-                        tokio::time::Instant::now() + Duration::from_millis((ts - unix_now) as u64);
-                    tokio::time::sleep_until(until).await;
-                    continue;
-                }
-            }
-        }
+    let loc = Url::parse(resp.headers().get("location").unwrap().to_str()?)?;
+    let queue = Queue::new(&loc, format, client)?;
+
+    if !options.wait {
+        format.print(&queue.info(), |q| println!("{}", q.url))?;
+        return Ok(ExitCode::SUCCESS);
+    }
 
-        if let Some(task) = queue_resp.task {
-            if let Some(exec) = queue_resp.executable {
-                println!("Tailing job {} #{}:", task.name, exec.number);
-                tail(exec.url, config).await?;
-                break;
+    let Some((task, exec)) = queue.wait(options.queue_timeout).await? else {
+        return Ok(ExitCode::from(INTERRUPTED));
+    };
+
+    format.progress(format!("Tailing job {} #{}:", task.name, exec.number));
+    let build = JobBuild::parse(&exec.url)?;
+    // Waiting on the queue installed a Ctrl-C handler, which stops the
+    // process from ending on SIGINT, so we have to handle it from here on
+    tokio::select! {
+        result = follow(&build, format, client) => result,
+        _ = tokio::signal::ctrl_c() => {
+            if options.abort_on_interrupt {
+                signal(&build, StopSignal::Stop, client).await?;
+                eprintln!("Error: Interrupted, aborted {}", build);
+            } else {
+                eprintln!("Error: Interrupted, {} is still running", build);
             }
+            Ok(ExitCode::from(INTERRUPTED))
         }
-
-        tokio::time::sleep(Duration::from_secs(i)).await;
     }
+}
 
-    Ok(())
+// Tails the build until it ends, and returns the exit code for its result
+async fn follow(build: &JobBuild, format: Format, client: &JenkinsClient) -> Result<ExitCode> {
+    let tail_options = TailOptions::default();
+    tail(build.url()?.to_string(), &tail_options, format, client).await?;
+
+    // The log can be complete slightly before the result is recorded
+    let job = build.job().clone();
+    let build = BuildRef::Number(build.clone());
+    loop {
+        let info = BuildInfo::fetch(&build, client).await?;
+        if !info.building {
+            format.print_record(&info.summary(&job), |_| {})?;
+            return Ok(BuildResult::exit_code(info.result));
+        }
+        tokio::time::sleep(Duration::from_secs(1)).await;
+    }
 }
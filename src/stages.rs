@@ -0,0 +1,241 @@
+use std::time::Duration;
+
+use anyhow::Result;
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    client::JenkinsClient,
+    job::{BuildRef, Permalink},
+    output::Format,
+    status::format_duration,
+};
+
+lazy_static! {
+    static ref TAG: Regex = Regex::new(r"<[^>]*>").unwrap();
+}
+
+#[derive(Deserialize, Debug)]
+struct Link {
+    href: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct StageLinks {
+    #[serde(rename = "self")]
+    this: Link,
+}
+
+#[derive(Deserialize, Debug)]
+struct NodeLinks {
+    log: Option<Link>,
+}
+
+// A run as described by the Pipeline Stage View plugin
+#[derive(Deserialize, Debug)]
+pub(crate) struct RunDescription {
+    pub(crate) status: String,
+    #[serde(default)]
+    pub(crate) stages: Vec<Stage>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct Stage {
+    pub(crate) id: String,
+    pub(crate) name: String,
+    pub(crate) status: String,
+    start_time_millis: u64,
+    duration_millis: u64,
+    #[serde(default)]
+    pause_duration_millis: u64,
+    #[serde(rename = "_links")]
+    links: StageLinks,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct StageDescription {
+    #[serde(default)]
+    stage_flow_nodes: Vec<FlowNode>,
+}
+
+#[derive(Deserialize, Debug)]
+pub(crate) struct FlowNode {
+    pub(crate) id: String,
+    pub(crate) name: String,
+    pub(crate) status: String,
+    #[serde(rename = "_links")]
+    links: NodeLinks,
+}
+
+// The log of a step. Jenkins only returns the end of long logs, marked
+// with `hasMore`, starting anywhere in a line.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct NodeLog {
+    #[serde(default)]
+    pub(crate) text: String,
+    // Of the whole log, console notes included
+    #[serde(default)]
+    pub(crate) length: u64,
+    #[serde(default)]
+    pub(crate) has_more: bool,
+    #[serde(default)]
+    pub(crate) console_url: Option<String>,
+}
+
+// The log of a step of a stage, as printed in the structured output formats
+#[derive(Serialize, Debug)]
+pub struct StepLog {
+    pub id: String,
+    pub name: String,
+    pub status: String,
+    pub text: String,
+}
+
+// A pipeline stage, as printed in the structured output formats
+#[derive(Serialize, Debug)]
+pub struct StageSummary {
+    pub id: String,
+    pub name: String,
+    // As reported by Jenkins, as in SUCCESS, FAILED or IN_PROGRESS
+    pub status: String,
+    pub timestamp_ms: u64,
+    pub duration_ms: u64,
+    // Time spent waiting for input
+    pub pause_duration_ms: u64,
+    // Only fetched for failed stages, with --failed-logs
+    pub logs: Vec<StepLog>,
+}
+
+// Node logs are HTML, with console annotations as tags
+fn strip_html(text: &str) -> String {
+    TAG.replace_all(text, "")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#039;", "'")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
+}
+
+// Runs, stages and steps waiting for input are still running
+pub(crate) fn is_running(status: &str) -> bool {
+    matches!(status, "IN_PROGRESS" | "PAUSED_PENDING_INPUT")
+}
+
+pub(crate) async fn describe_run(
+    build: &BuildRef,
+    client: &JenkinsClient,
+) -> Result<RunDescription> {
+    client.get_json(build.url()?.join("wfapi/describe")?).await
+}
+
+// The steps of a stage, in the order they ran
+pub(crate) async fn stage_nodes(stage: &Stage, client: &JenkinsClient) -> Result<Vec<FlowNode>> {
+    let url = client.base_url().join(&stage.links.this.href)?;
+    let description: StageDescription = client.get_json(url).await?;
+    Ok(description.stage_flow_nodes)
+}
+
+// The log of a step, as plain text. Steps that don't log have no link.
+pub(crate) async fn node_log(node: &FlowNode, client: &JenkinsClient) -> Result<Option<NodeLog>> {
+    let Some(log) = &node.links.log else {
+        return Ok(None);
+    };
+    let mut log: NodeLog = client.get_json(client.base_url().join(&log.href)?).await?;
+    log.text = strip_html(&log.text);
+    Ok(Some(log))
+}
+
+// The logs of the failed steps of a stage, or of all of them if none is
+// marked as failed, as when the stage failed on its own
+async fn failed_logs(stage: &Stage, client: &JenkinsClient) -> Result<Vec<StepLog>> {
+    let nodes = stage_nodes(stage, client).await?;
+    let failed: Vec<_> = nodes.iter().filter(|n| n.status == "FAILED").collect();
+    let nodes = if failed.is_empty() {
+        nodes.iter().collect()
+    } else {
+        failed
+    };
+
+    let mut logs = vec![];
+    for node in nodes {
+        if let Some(log) = node_log(node, client).await? {
+            logs.push(StepLog {
+                id: node.id.clone(),
+                name: node.name.clone(),
+                status: node.status.clone(),
+                text: log.text,
+            });
+        }
+    }
+    Ok(logs)
+}
+
+pub async fn stages(
+    job: String,
+    with_failed_logs: bool,
+    format: Format,
+    client: &JenkinsClient,
+) -> Result<()> {
+    let build = BuildRef::new(&client.resolve(&job)?, Permalink::LastBuild)?;
+    let description = describe_run(&build, client).await?;
+
+    let mut stages = vec![];
+    for stage in &description.stages {
+        let logs = if with_failed_logs && stage.status == "FAILED" {
+            failed_logs(stage, client).await?
+        } else {
+            vec![]
+        };
+        stages.push(StageSummary {
+            id: stage.id.clone(),
+            name: stage.name.clone(),
+            status: stage.status.clone(),
+            timestamp_ms: stage.start_time_millis,
+            duration_ms: stage.duration_millis,
+            pause_duration_ms: stage.pause_duration_millis,
+            logs,
+        });
+    }
+
+    format.print(&stages, |stages| {
+        println!("{}", build);
+        for stage in stages {
+            let duration = format_duration(Duration::from_millis(stage.duration_ms));
+            if stage.pause_duration_ms > 0 {
+                let paused = format_duration(Duration::from_millis(stage.pause_duration_ms));
+                println!(
+                    "{:<12} {:>10}  {} (paused {})",
+                    stage.status, duration, stage.name, paused
+                );
+            } else {
+                println!("{:<12} {:>10}  {}", stage.status, duration, stage.name);
+            }
+            for log in &stage.logs {
+                println!("  {} ({}):", log.name, log.status);
+                for line in log.text.lines() {
+                    println!("    {}", line);
+                }
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_html() {
+        assert_eq!(
+            strip_html(
+                "<span class=\"pipeline-node-8\">+ make test\nexit &lt;1&gt; &amp; done</span>"
+            ),
+            "+ make test\nexit <1> & done"
+        );
+    }
+}
@@ -0,0 +1,180 @@
+use std::{
+    fmt::Display,
+    process::ExitCode,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::Result;
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    client::JenkinsClient,
+    job::{BuildRef, Job, Permalink},
+    output::Format,
+};
+
+pub(crate) const BUILD_TREE: &str =
+    "number,url,result,building,duration,timestamp,actions[causes[shortDescription]]";
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+#[value(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum BuildResult {
+    Success,
+    Unstable,
+    Failure,
+    NotBuilt,
+    Aborted,
+}
+
+impl BuildResult {
+    // Scripts branch on these, so they must never change. Errors exit
+    // with 1 and usage errors with 2, so results start at 10. Builds that
+    // haven't finished yet exit with 14.
+    pub fn exit_code(result: Option<BuildResult>) -> ExitCode {
+        ExitCode::from(match result {
+            Some(BuildResult::Success) => 0,
+            Some(BuildResult::Unstable) => 10,
+            Some(BuildResult::Failure) => 11,
+            Some(BuildResult::Aborted) => 12,
+            Some(BuildResult::NotBuilt) => 13,
+            None => 14,
+        })
+    }
+}
+
+impl Display for BuildResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            BuildResult::Success => "SUCCESS",
+            BuildResult::Unstable => "UNSTABLE",
+            BuildResult::Failure => "FAILURE",
+            BuildResult::NotBuilt => "NOT_BUILT",
+            BuildResult::Aborted => "ABORTED",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct Cause {
+    #[serde(rename = "shortDescription")]
+    short_description: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct Action {
+    #[serde(default)]
+    causes: Vec<Cause>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct BuildInfo {
+    pub number: u32,
+    pub url: String,
+    pub result: Option<BuildResult>,
+    pub building: bool,
+    // milliseconds, 0 while the build is running
+    pub duration: u64,
+    // milliseconds since the epoch
+    pub timestamp: u64,
+    #[serde(default)]
+    actions: Vec<Action>,
+}
+
+// The state of a build, as printed in the structured output formats
+#[derive(Serialize, Debug)]
+pub struct BuildSummary {
+    // The job display name and build number, as in `folder » job #12`
+    pub build: String,
+    pub url: String,
+    pub number: u32,
+    // null while the build is running
+    pub result: Option<BuildResult>,
+    pub building: bool,
+    // RFC 3339, in UTC
+    pub started: String,
+    pub timestamp_ms: u64,
+    pub duration_ms: u64,
+    pub causes: Vec<String>,
+}
+
+impl BuildInfo {
+    pub fn summary(&self, job: &Job) -> BuildSummary {
+        BuildSummary {
+            build: job.build(self.number).to_string(),
+            url: self.url.clone(),
+            number: self.number,
+            result: self.result,
+            building: self.building,
+            started: humantime::format_rfc3339_seconds(self.started()).to_string(),
+            timestamp_ms: self.timestamp,
+            duration_ms: self.duration().as_millis() as u64,
+            causes: self.causes().into_iter().map(str::to_owned).collect(),
+        }
+    }
+
+    pub async fn fetch(build: &BuildRef, client: &JenkinsClient) -> Result<BuildInfo> {
+        client.get_json(build.api_path(BUILD_TREE)?).await
+    }
+
+    pub fn causes(&self) -> Vec<&str> {
+        self.actions
+            .iter()
+            .flat_map(|a| a.causes.iter())
+            .map(|c| c.short_description.as_str())
+            .collect()
+    }
+
+    pub fn started(&self) -> SystemTime {
+        UNIX_EPOCH + Duration::from_millis(self.timestamp)
+    }
+
+    // Running builds report no duration, so we use the time elapsed
+    // since they started
+    pub fn duration(&self) -> Duration {
+        if self.building {
+            SystemTime::now()
+                .duration_since(self.started())
+                .unwrap_or_default()
+        } else {
+            Duration::from_millis(self.duration)
+        }
+    }
+}
+
+pub(crate) fn format_duration(d: Duration) -> String {
+    humantime::format_duration(Duration::from_secs(d.as_secs())).to_string()
+}
+
+pub async fn status(
+    job: String,
+    permalink: Option<Permalink>,
+    format: Format,
+    client: &JenkinsClient,
+) -> Result<ExitCode> {
+    let url = client.resolve(&job)?;
+    let build = match permalink {
+        Some(p) => BuildRef::Permalink(Job::new(&url)?, p),
+        None => BuildRef::new(&url, Permalink::LastBuild)?,
+    };
+
+    let info = BuildInfo::fetch(&build, client).await?;
+    format.print(&info.summary(build.job()), |summary| {
+        let result = match summary.result {
+            Some(r) => r.to_string(),
+            None => "-".to_owned(),
+        };
+        println!("{}", summary.build);
+        println!("result: {}", result);
+        println!("building: {}", summary.building);
+        println!("started: {}", summary.started);
+        println!("duration: {}", format_duration(info.duration()));
+        for cause in &summary.causes {
+            println!("cause: {}", cause);
+        }
+    })?;
+
+    Ok(BuildResult::exit_code(info.result))
+}
@@ -0,0 +1,97 @@
+use std::time::Duration;
+
+use anyhow::{bail, Result};
+use serde::Serialize;
+use tokio::time::Instant;
+
+use crate::{
+    client::JenkinsClient,
+    job::{BuildRef, JobBuild},
+    output::Format,
+    status::BuildInfo,
+};
+
+// The ways Jenkins can abort a build, from gentlest to most forceful.
+// Term and Kill only apply to Pipeline builds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopSignal {
+    Stop,
+    Term,
+    Kill,
+}
+
+impl StopSignal {
+    fn path(&self) -> &str {
+        match self {
+            StopSignal::Stop => "stop",
+            StopSignal::Term => "term",
+            StopSignal::Kill => "kill",
+        }
+    }
+}
+
+// A stopped build, as printed in the structured output formats
+#[derive(Serialize, Debug)]
+pub struct StopSummary {
+    pub build: String,
+    pub url: String,
+    // The signals sent, in order: stop, term and kill
+    pub signals: Vec<String>,
+}
+
+pub async fn signal(build: &JobBuild, signal: StopSignal, client: &JenkinsClient) -> Result<()> {
+    let url = build.url()?.join(signal.path())?;
+    client.send(client.post(url).await?).await?;
+    Ok(())
+}
+
+async fn wait_until_stopped(
+    build: &JobBuild,
+    grace: Duration,
+    client: &JenkinsClient,
+) -> Result<bool> {
+    let deadline = Instant::now() + grace;
+    let build = BuildRef::Number(build.clone());
+    loop {
+        if !BuildInfo::fetch(&build, client).await?.building {
+            return Ok(true);
+        }
+        if Instant::now() >= deadline {
+            return Ok(false);
+        }
+        tokio::time::sleep(Duration::from_secs(1)).await;
+    }
+}
+
+// Sends each signal in turn, moving on to the next one only if the build
+// is still running after the grace period. A single signal is sent without
+// waiting.
+pub async fn stop(
+    job: String,
+    signals: &[StopSignal],
+    grace: Duration,
+    format: Format,
+    client: &JenkinsClient,
+) -> Result<()> {
+    let build = JobBuild::new(&client.resolve(&job)?)?;
+    let mut summary = StopSummary {
+        build: build.to_string(),
+        url: build.url()?.to_string(),
+        signals: vec![],
+    };
+
+    for s in signals {
+        format.progress(format!("Sending {} to {}...", s.path(), build));
+        signal(&build, *s, client).await?;
+        summary.signals.push(s.path().to_owned());
+        if signals.len() == 1 || wait_until_stopped(&build, grace, client).await? {
+            return format.print(&summary, |_| {});
+        }
+    }
+
+    bail!(
+        "{} is still running after {}",
+        build,
+        humantime::format_duration(grace)
+    );
+}
@@ -1,100 +1,699 @@
-use anyhow::Result;
+use anyhow::{anyhow, bail, Result};
+use clap::ValueEnum;
 use lazy_static::lazy_static;
 use regex::Regex;
-use reqwest::Client;
-use reqwest::Url;
-use std::{sync::Arc, time::Duration};
+use reqwest::{Response, Url};
+use serde::Serialize;
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap, HashSet},
+    hash::{Hash, Hasher},
+    io::{IsTerminal, Write},
+    sync::Arc,
+    time::Duration,
+};
 use tokio::{
-    sync::mpsc::{channel, Sender},
+    sync::{
+        mpsc::{channel, Sender},
+        Semaphore,
+    },
     task::JoinSet,
+    time::Instant,
 };
 
-use crate::{config::JenkinsConfig, job::JobBuild};
+use crate::{
+    client::JenkinsClient,
+    downstream::Downstream,
+    input::Prompts,
+    job::{BuildRef, JobBuild, Permalink},
+    output::Format,
+    stages::{describe_run, is_running, node_log, stage_nodes, NodeLog},
+    status::BuildInfo,
+};
 
 lazy_static! {
     static ref SUB_BUILD: Regex =
         Regex::new(r"^Starting building: (?P<job_name>.+) #(?P<job_number>\d+)$").unwrap();
 }
 
+// How often `--follow-job` checks for a new build once the last one ended
+const FOLLOW_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+// How often to look for downstream builds through the API while a build
+// runs. Each lookup takes a request per downstream project.
+const DISCOVERY_INTERVAL: Duration = Duration::from_secs(10);
+
+// A guess at the length of log lines, to know how far back to start
+// reading for `--lines`
+const LINE_LENGTH_GUESS: u64 = 160;
+
+// A downstream build to tail, and how many triggers away it is from the
+// build given on the command line
 #[derive(Debug)]
-struct NewTask(Url, Sender<NewTask>);
+struct NewTask(Url, usize, Sender<NewTask>);
 
-async fn _tail(job: Url, config: Arc<JenkinsConfig>, tx: Sender<NewTask>) -> Result<()> {
-    let client = Client::new();
-    let mut start = 0;
-    let profile = config.profile()?;
+// What goes before each log line in text output
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum Prefix {
+    None,
+    // The job name and build number, as in `job #12`
+    Short,
+    // The full display name of the build, as in `folder » job #12`
+    #[default]
+    Full,
+}
 
-    let build = JobBuild::new(&job)?;
+pub const DEFAULT_MAX_CONCURRENT_TAILS: usize = 8;
+
+#[derive(Debug)]
+pub struct TailOptions {
+    // Once the build ends, wait for the next build of the job and tail
+    // it, forever
+    pub follow_job: bool,
+    // Only show the last lines of the log of the build, and what follows
+    pub lines: Option<usize>,
+    // Skip this many bytes of the log of the build
+    pub start_byte: Option<u64>,
+    // Print the logs as they are now, instead of waiting for them to end
+    pub no_follow: bool,
+    pub prefix: Prefix,
+    // Indent downstream builds under the build that started them
+    pub tree: bool,
+    // Print the log of each build at once, when it ends
+    pub group: bool,
+    // Downstream builds over the limit wait for others to end
+    pub max_concurrent_tails: usize,
+    // How many triggers away from the build to tail downstream builds,
+    // None for no limit and 0 for none at all
+    pub max_depth: Option<usize>,
+    // Only tail the steps of this pipeline stage
+    pub stage: Option<String>,
+    // Name the pipeline stage of each line
+    pub by_stage: bool,
+}
+
+impl Default for TailOptions {
+    fn default() -> Self {
+        TailOptions {
+            follow_job: false,
+            lines: None,
+            start_byte: None,
+            no_follow: false,
+            prefix: Prefix::default(),
+            tree: false,
+            group: false,
+            max_concurrent_tails: DEFAULT_MAX_CONCURRENT_TAILS,
+            max_depth: None,
+            stage: None,
+            by_stage: false,
+        }
+    }
+}
+
+// How log lines are shown
+#[derive(Debug, Clone, Copy)]
+struct Render {
+    format: Format,
+    prefix: Prefix,
+    tree: bool,
+    group: bool,
+    color: bool,
+}
+
+const COLORS: [&str; 10] = [
+    "\x1b[32m", "\x1b[33m", "\x1b[34m", "\x1b[35m", "\x1b[36m", "\x1b[92m", "\x1b[93m", "\x1b[94m",
+    "\x1b[95m", "\x1b[96m",
+];
+
+impl Render {
+    // Each build gets a color of its own, the same every time it's tailed
+    fn color(build: &JobBuild) -> &'static str {
+        let mut hasher = DefaultHasher::new();
+        build.hash(&mut hasher);
+        COLORS[(hasher.finish() % COLORS.len() as u64) as usize]
+    }
+
+    fn text(&self, build: &JobBuild, depth: usize, line: &str) -> String {
+        let indent = if self.tree {
+            "  ".repeat(depth)
+        } else {
+            String::new()
+        };
+        let prefix = match self.prefix {
+            Prefix::None => None,
+            Prefix::Short => Some(format!("{} #{}", build.job().name(), build.number())),
+            Prefix::Full => Some(build.to_string()),
+        };
+        match (prefix, self.color) {
+            (Some(prefix), true) => {
+                format!(
+                    "{}{}{}:\x1b[0m {}",
+                    indent,
+                    Render::color(build),
+                    prefix,
+                    line
+                )
+            }
+            (Some(prefix), false) => format!("{}{}: {}", indent, prefix, line),
+            (None, true) => format!("{}{}{}\x1b[0m", indent, Render::color(build), line),
+            (None, false) => format!("{}{}", indent, line),
+        }
+    }
+}
+
+// Where to start reading a build log
+#[derive(Debug, Clone, Copy)]
+enum Offset {
+    Byte(u64),
+    Lines(usize),
+}
+
+// How to read the log of one of the builds being tailed
+#[derive(Debug, Clone, Copy)]
+struct Reading {
+    // How many triggers away the build is from the one tailed
+    depth: usize,
+    offset: Offset,
+    follow: bool,
+    // Whether to look for the builds it starts
+    subjobs: bool,
+    // Whether to name the pipeline stage of each line
+    by_stage: bool,
+}
+
+fn text_size(resp: &Response) -> Result<Option<u64>> {
+    match resp.headers().get("x-text-size") {
+        Some(ts) => Ok(Some(ts.to_str()?.parse()?)),
+        None => Ok(None),
+    }
+}
+
+// A piece of a build log, and where the next one starts
+struct Chunk {
+    text: String,
+    more: bool,
+    next: u64,
+}
+
+async fn read_log(build: &JobBuild, start: u64, client: &JenkinsClient) -> Result<Chunk> {
+    let resp = client.send(client.get(build.log_path(start)?)).await?;
+    let more_data = resp.headers().get("x-more-data").cloned();
+    let text_size = text_size(&resp)?;
+    let text = resp.text().await?;
+
+    let mut chunk = Chunk {
+        text,
+        more: false,
+        next: start,
+    };
+    if let (Some(md), Some(ts)) = (more_data, text_size) {
+        chunk.more = md == "true";
+        chunk.next = ts;
+    }
+    Ok(chunk)
+}
+
+// Reads the last `lines` lines of a build log. Offsets count the bytes of
+// the log file, console notes included, while the text Jenkins returns has
+// them stripped, so no offset can be worked out from the text. Instead we
+// read growing windows back from the end of the log, dropping the line the
+// window starts in, until they hold enough lines, and go on from the end of
+// the last window.
+async fn seek_lines(build: &JobBuild, lines: usize, client: &JenkinsClient) -> Result<Chunk> {
+    let resp = client.send(client.head(build.log_path(0)?)).await?;
+    let size = text_size(&resp)?.unwrap_or_default();
+    if lines == 0 {
+        return Ok(Chunk {
+            text: String::new(),
+            more: true,
+            next: size,
+        });
+    }
+
+    let mut window = (lines as u64 + 1) * LINE_LENGTH_GUESS;
     loop {
-        let resp = client
-            .get(build.log_path(start)?)
-            .basic_auth(&profile.username, Some(&profile.password))
-            .send()
-            .await?;
+        let start = size.saturating_sub(window);
+        let mut chunk = read_log(build, start, client).await?;
+        // Windows start anywhere in a line, or in a console note
+        let whole = if start > 0 {
+            chunk.text.find('\n').map_or(chunk.text.len(), |i| i + 1)
+        } else {
+            0
+        };
+        let text = &chunk.text[whole..];
+        let starts: Vec<_> = std::iter::once(0)
+            .chain(text.match_indices('\n').map(|(i, _)| i + 1))
+            .filter(|&i| i < text.len())
+            .collect();
+        if starts.len() >= lines {
+            chunk.text = text[starts[starts.len() - lines]..].to_owned();
+            return Ok(chunk);
+        }
+        if start == 0 {
+            chunk.text = text.to_owned();
+            return Ok(chunk);
+        }
+        window *= 2;
+    }
+}
+
+// The pipeline stage a build log is in, as told by the lines Jenkins logs
+// around each stage:
+//
+//   [Pipeline] stage
+//   [Pipeline] { (Build)
+//   ...
+//   [Pipeline] }
+//   [Pipeline] // stage
+//
+// Stages can be nested, so the stages the log is in are kept as a stack.
+#[derive(Debug, Default)]
+struct StageMarkers {
+    stages: Vec<String>,
+    // Whether the last line opened a stage, so the next one names it
+    opening: bool,
+}
+
+impl StageMarkers {
+    // The stage of a line, with the lines opening and closing a stage
+    // counted in it
+    fn stage_of(&mut self, line: &str) -> Option<String> {
+        let opening = std::mem::take(&mut self.opening);
+        if line == "[Pipeline] stage" {
+            self.opening = true;
+        } else if let Some(name) = line
+            .strip_prefix("[Pipeline] { (")
+            .and_then(|l| l.strip_suffix(')'))
+            .filter(|_| opening)
+        {
+            self.stages.push(name.to_owned());
+        } else if line == "[Pipeline] // stage" {
+            return self.stages.pop();
+        }
+        self.stages.last().cloned()
+    }
+}
+
+// A line of a build log, as printed in the structured output formats
+#[derive(Serialize, Debug)]
+pub struct LogLine<'a> {
+    pub build: String,
+    pub url: String,
+    pub line: &'a str,
+    // Only with --stage or --by-stage
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stage: Option<&'a str>,
+}
 
-        let more_data = resp.headers().get("x-more-data").cloned();
-        let text_size = resp.headers().get("x-text-size").cloned();
+async fn _tail(
+    job: Url,
+    reading: Reading,
+    render: Render,
+    client: JenkinsClient,
+    tx: Sender<NewTask>,
+) -> Result<()> {
+    let Reading {
+        depth,
+        offset,
+        follow,
+        subjobs,
+        by_stage,
+    } = reading;
+    let build = JobBuild::new(&job)?;
+    let mut chunk = match offset {
+        Offset::Byte(start) => read_log(&build, start, &client).await?,
+        Offset::Lines(lines) => seek_lines(&build, lines, &client).await?,
+    };
 
-        for line in resp.text().await?.lines() {
-            if let Some(captures) = SUB_BUILD.captures(line) {
+    let name = build.to_string();
+    let url = build.url()?.to_string();
+    let mut downstream = Downstream::new(&build);
+    let mut discovered: Option<Instant> = None;
+    let mut prompts = Prompts::new(&build);
+    let mut markers = StageMarkers::default();
+    let mut grouped = vec![];
+    loop {
+        let Chunk { text, more, next } = chunk;
+        for line in text.lines() {
+            if let Some(captures) = SUB_BUILD.captures(line).filter(|_| subjobs) {
                 let job = captures.name("job_name").unwrap().as_str().to_owned();
                 let job = job.replace(" » ", "/job/");
                 let number: u32 = captures.name("job_number").unwrap().as_str().parse()?;
 
                 tx.send(NewTask(
-                    profile.url()?.join(&format!("/job/{}/{}", job, number))?,
+                    client
+                        .base_url()
+                        .join(&format!("/job/{}/{}", job, number))?,
+                    depth + 1,
                     tx.clone(),
                 ))
                 .await?;
             }
-            println!("{build}: {line}");
+            prompts.note(line);
+            let stage = by_stage.then(|| markers.stage_of(line)).flatten();
+            let line = LogLine {
+                build: name.clone(),
+                url: url.clone(),
+                line,
+                stage: stage.as_deref(),
+            };
+            render.format.print_record(&line, |l| {
+                let text = match l.stage {
+                    Some(stage) => render.text(&build, depth, &format!("[{}] {}", stage, l.line)),
+                    None => render.text(&build, depth, l.line),
+                };
+                if render.group {
+                    grouped.push(text);
+                } else {
+                    println!("{}", text);
+                }
+            })?;
         }
 
-        let mut more = false;
-        if let Some(md) = more_data {
-            if let Some(ts) = text_size {
-                if md == "true" {
-                    more = true;
+        // Builds started on completion only show up once the log ends, so
+        // we always look then. The lookup is best effort: Jenkins answers
+        // 404 for projects we can't read.
+        let due = discovered.is_none_or(|t| t.elapsed() >= DISCOVERY_INTERVAL);
+        if subjobs && (due || !more || !follow) {
+            discovered = Some(Instant::now());
+            if let Ok(urls) = downstream.discover(&client).await {
+                for url in urls {
+                    tx.send(NewTask(url, depth + 1, tx.clone())).await?;
                 }
-                start = ts.to_str()?.parse()?;
             }
         }
 
-        if !more {
+        if !more || !follow {
             break;
         }
 
+        // A log that stops growing may be a pipeline waiting for input
+        if text.is_empty() {
+            prompts.show(render.format, &client).await?;
+        }
+
         tokio::time::sleep(Duration::from_secs(1)).await;
+        chunk = read_log(&build, next, &client).await?;
+    }
+
+    if !grouped.is_empty() {
+        let mut stdout = std::io::stdout().lock();
+        for line in grouped {
+            writeln!(stdout, "{}", line)?;
+        }
     }
 
     Ok(())
 }
 
-pub async fn tail(job: String, config: JenkinsConfig) -> Result<()> {
+// Job urls without a build number, or with a permalink, tail the build
+// the permalink points to at the time, the last one by default
+async fn resolve_build(url: &Url, format: Format, client: &JenkinsClient) -> Result<JobBuild> {
+    let build = match BuildRef::new(url, Permalink::LastBuild)? {
+        BuildRef::Number(build) => return Ok(build),
+        build => build,
+    };
+    let info = BuildInfo::fetch(&build, client)
+        .await
+        .map_err(|e| anyhow!("Failed to find {}: {}", build, e))?;
+    let resolved = build.job().build(info.number);
+    format.progress(format!("Tailing {}", resolved));
+    Ok(resolved)
+}
+
+// Tails a build and the downstream builds it starts, until they all end.
+// The offset only applies to the build, downstream builds are read whole.
+async fn tail_build(
+    build: &JobBuild,
+    offset: Offset,
+    options: &TailOptions,
+    render: Render,
+    client: &JenkinsClient,
+) -> Result<()> {
     let (tx, mut rx) = channel(8);
+    let permits = Arc::new(Semaphore::new(options.max_concurrent_tails));
+    let follow = !options.no_follow;
 
-    let url = if job.starts_with('/') {
-        config.profile()?.url()?.join(&job)?
-    } else {
-        Url::parse(&job)?
+    let mut tasks = JoinSet::new();
+    let mut spawn = |build: JobBuild, depth: usize, offset: Offset, tx: Sender<NewTask>| {
+        let reading = Reading {
+            depth,
+            offset,
+            follow,
+            subjobs: options.max_depth.is_none_or(|d| depth < d),
+            by_stage: options.by_stage,
+        };
+        let permits = permits.clone();
+        let client = client.clone();
+        tasks.spawn(async move {
+            let _permit = permits.acquire_owned().await?;
+            _tail(build.url()?, reading, render, client, tx)
+                .await
+                .map_err(|e| anyhow!("Failed to tail {}: {}", build, e))
+        });
     };
 
-    let cfg = Arc::new(config);
-    let mut tasks = JoinSet::new();
-    tasks.spawn(_tail(url.clone(), cfg.clone(), tx));
+    // Downstream builds can be found both in the log and through the API,
+    // and more than one build can start the same one
+    let normalize = |url: &Url| JobBuild::new(&client.base_url().join(url.path())?);
+    let mut seen = HashSet::new();
+    seen.insert(normalize(&build.url()?)?);
+    spawn(build.clone(), 0, offset, tx);
 
+    // One build failing doesn't stop the others, nor does a downstream
+    // build we can't make out, like a branch with a slash in its name
+    let mut failed = 0;
+    let mut unknown = 0;
     while let Some(msg) = rx.recv().await {
-        let NewTask(url, tx) = msg;
-        tasks.spawn(_tail(url.clone(), cfg.clone(), tx));
+        let NewTask(url, depth, tx) = msg;
+        let build = match normalize(&url) {
+            Ok(build) => build,
+            Err(e) => {
+                eprintln!("Error: Failed to tail {}: {}", url, e);
+                unknown += 1;
+                continue;
+            }
+        };
+        if seen.insert(build.clone()) {
+            spawn(build, depth, Offset::Byte(0), tx);
+        }
     }
 
     while let Some(result) = tasks.join_next().await {
-        result??;
+        if let Err(e) = result.map_err(anyhow::Error::from).and_then(|r| r) {
+            eprintln!("Error: {}", e);
+            failed += 1;
+        }
+    }
+    if failed + unknown > 0 {
+        bail!(
+            "Failed to tail {} of {} builds",
+            failed + unknown,
+            seen.len() + unknown
+        );
     }
 
     Ok(())
 }
 
+// How much of the log of a step has been printed
+#[derive(Debug, Default)]
+struct StepProgress {
+    // Of the whole log when last read, console notes included
+    length: u64,
+    // Lines printed, while the whole log is returned
+    lines: usize,
+    last: Option<String>,
+    // Whether part of the log was skipped, which is only said once
+    truncated: bool,
+}
+
+impl StepProgress {
+    // The lines of the log not printed yet, and whether they follow the
+    // ones printed before. Once a log outgrows what Jenkins returns, only
+    // its end is available, so we go on from the last line printed.
+    fn new_lines<'a>(&mut self, log: &'a NodeLog, running: bool) -> (Vec<&'a str>, bool) {
+        if running && log.length > 0 && log.length == self.length {
+            return (vec![], true);
+        }
+        // Lines of running steps may not be complete yet
+        let text = if running {
+            &log.text[..log.text.rfind('\n').map_or(0, |i| i + 1)]
+        } else {
+            &log.text
+        };
+        let mut lines: Vec<&str> = text.lines().collect();
+        if log.has_more && !lines.is_empty() {
+            lines.remove(0);
+        }
+
+        let (start, complete) = if !log.has_more {
+            (self.lines.min(lines.len()), true)
+        } else {
+            let last = self.last.as_deref();
+            match last.and_then(|last| lines.iter().rposition(|l| *l == last)) {
+                Some(i) => (i + 1, true),
+                None => (0, false),
+            }
+        };
+        let new = lines.split_off(start);
+        self.lines = start + new.len();
+        if let Some(last) = new.last() {
+            self.last = Some(last.to_string());
+        }
+        if !running || !new.is_empty() {
+            self.length = log.length;
+        }
+        (new, complete)
+    }
+}
+
+// Tails the logs of the steps of a stage of a pipeline build, through the
+// workflow API. Running steps are read again on every poll, since their
+// logs can't be read from an offset, and only their new lines are printed.
+async fn tail_stage(
+    build: &JobBuild,
+    stage: &str,
+    options: &TailOptions,
+    render: Render,
+    client: &JenkinsClient,
+) -> Result<()> {
+    let name = build.to_string();
+    let url = build.url()?.to_string();
+    let build_ref = BuildRef::Number(build.clone());
+
+    let mut printed: HashMap<String, StepProgress> = HashMap::new();
+    let mut done_nodes = HashSet::new();
+    let mut done_stages = HashSet::new();
+    let mut prompts = Prompts::new(build);
+    loop {
+        let run = describe_run(&build_ref, client).await?;
+        let running = is_running(&run.status);
+
+        let stages: Vec<_> = run.stages.iter().filter(|s| s.name == stage).collect();
+        if stages.is_empty() && !running {
+            let names: Vec<_> = run.stages.iter().map(|s| s.name.as_str()).collect();
+            bail!(
+                "{} has no stage named {}. Its stages are: {}",
+                build,
+                stage,
+                names.join(", ")
+            );
+        }
+
+        for stage in stages {
+            if done_stages.contains(&stage.id) {
+                continue;
+            }
+            for node in stage_nodes(stage, client).await? {
+                if done_nodes.contains(&node.id) {
+                    continue;
+                }
+                let node_running = is_running(&node.status);
+                let Some(log) = node_log(&node, client).await? else {
+                    done_nodes.insert(node.id);
+                    continue;
+                };
+                let progress = printed.entry(node.id.clone()).or_default();
+                let (lines, complete) = progress.new_lines(&log, node_running);
+                if !complete && !progress.truncated {
+                    progress.truncated = true;
+                    eprintln!(
+                        "Warning: only the end of the log of step {} is available{}",
+                        node.name,
+                        log.console_url
+                            .as_ref()
+                            .map(|u| format!(", see {}", u))
+                            .unwrap_or_default()
+                    );
+                }
+                for line in lines {
+                    let line = LogLine {
+                        build: name.clone(),
+                        url: url.clone(),
+                        line,
+                        stage: Some(&stage.name),
+                    };
+                    render.format.print_record(&line, |l| {
+                        println!("{}", render.text(build, 0, l.line));
+                    })?;
+                }
+                if !node_running {
+                    done_nodes.insert(node.id);
+                }
+            }
+            if !is_running(&stage.status) {
+                done_stages.insert(stage.id.clone());
+            }
+        }
+
+        if !running || options.no_follow {
+            return Ok(());
+        }
+        if run.status == "PAUSED_PENDING_INPUT" {
+            prompts.show(render.format, client).await?;
+        }
+        tokio::time::sleep(Duration::from_secs(1)).await;
+    }
+}
+
+pub async fn tail(
+    job: String,
+    options: &TailOptions,
+    format: Format,
+    client: &JenkinsClient,
+) -> Result<()> {
+    let render = Render {
+        format,
+        prefix: options.prefix,
+        tree: options.tree,
+        group: options.group,
+        color: std::io::stdout().is_terminal(),
+    };
+    let mut build = resolve_build(&client.resolve(&job)?, format, client).await?;
+    let mut offset = match (options.lines, options.start_byte) {
+        (Some(lines), _) => Offset::Lines(lines),
+        (None, start) => Offset::Byte(start.unwrap_or_default()),
+    };
+    loop {
+        let result = if let Some(stage) = &options.stage {
+            tail_stage(&build, stage, options, render, client).await
+        } else {
+            tail_build(&build, offset, options, render, client).await
+        };
+        offset = Offset::Byte(0);
+        if !options.follow_job {
+            return result;
+        }
+        // A build that fails to tail shouldn't stop following the job
+        if let Err(e) = result {
+            eprintln!("Error: {}", e);
+        }
+
+        // Builds running at the same time as this one are skipped, only
+        // the latest one is followed. Failures are only reported once until
+        // a lookup succeeds.
+        let last = BuildRef::Permalink(build.job().clone(), Permalink::LastBuild);
+        let mut failing = false;
+        loop {
+            tokio::time::sleep(FOLLOW_POLL_INTERVAL).await;
+            match BuildInfo::fetch(&last, client).await {
+                Ok(info) if info.number > build.number() => {
+                    build = build.job().build(info.number);
+                    break;
+                }
+                Ok(_) => failing = false,
+                Err(e) => {
+                    if !failing {
+                        eprintln!("Warning: Failed to find {}: {}", last, e);
+                    }
+                    failing = true;
+                }
+            }
+        }
+        format.progress(format!("Tailing {}", build));
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::str::FromStr;
@@ -104,7 +703,100 @@ mod tests {
     #[test]
     fn test_newtask_debug() {
         let (tx, _) = channel(8);
-        let t = NewTask(Url::from_str("http://example.com").unwrap(), tx);
+        let t = NewTask(Url::from_str("http://example.com").unwrap(), 1, tx);
         assert!(format!("{:?}", t).contains("example.com"));
     }
+
+    #[test]
+    fn test_stage_markers() {
+        let log = "Started by user admin\n\
+            [Pipeline] stage\n\
+            [Pipeline] { (Build)\n\
+            compiling\n\
+            [Pipeline] stage\n\
+            [Pipeline] { (Unit)\n\
+            testing\n\
+            [Pipeline] }\n\
+            [Pipeline] // stage\n\
+            [Pipeline] { (not a stage)\n\
+            [Pipeline] }\n\
+            [Pipeline] }\n\
+            [Pipeline] // stage\n\
+            Finished: SUCCESS";
+        let mut markers = StageMarkers::default();
+        let stages: Vec<_> = log.lines().map(|l| markers.stage_of(l)).collect();
+        let stage = |name: &str| Some(name.to_owned());
+        assert_eq!(
+            stages,
+            vec![
+                None,
+                None,
+                stage("Build"),
+                stage("Build"),
+                stage("Build"),
+                stage("Unit"),
+                stage("Unit"),
+                stage("Unit"),
+                stage("Unit"),
+                stage("Build"),
+                stage("Build"),
+                stage("Build"),
+                stage("Build"),
+                None,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_step_progress() {
+        let log = |text: &str, length: u64, has_more: bool| NodeLog {
+            text: text.to_owned(),
+            length,
+            has_more,
+            console_url: None,
+        };
+        let mut progress = StepProgress::default();
+
+        let first = log("héllo\nwor", 10, false);
+        assert_eq!(progress.new_lines(&first, true), (vec!["héllo"], true));
+        let second = log("héllo\nworld\n", 13, false);
+        assert_eq!(progress.new_lines(&second, true), (vec!["world"], true));
+        assert_eq!(progress.new_lines(&second, true), (vec![], true));
+
+        // Once only the end is returned, we go on from the last line printed
+        let third = log("rld\nx\ny\n", 5000, true);
+        assert_eq!(progress.new_lines(&third, true), (vec!["x", "y"], false));
+        let last = log("x\ny\nz", 5010, true);
+        assert_eq!(progress.new_lines(&last, false), (vec!["z"], true));
+    }
+
+    #[test]
+    fn test_render_text() -> Result<()> {
+        let build = JobBuild::parse("http://example.com/job/folder/job/child/3/")?;
+        let mut render = Render {
+            format: Format::Text,
+            prefix: Prefix::Full,
+            tree: false,
+            group: false,
+            color: false,
+        };
+        assert_eq!(render.text(&build, 2, "line"), "folder » child #3: line");
+
+        render.tree = true;
+        render.prefix = Prefix::Short;
+        assert_eq!(render.text(&build, 2, "line"), "    child #3: line");
+
+        render.prefix = Prefix::None;
+        assert_eq!(render.text(&build, 1, "line"), "  line");
+
+        render.color = true;
+        let color = Render::color(&build);
+        assert_eq!(Render::color(&build.clone()), color);
+        assert_eq!(
+            render.text(&build, 0, "line"),
+            format!("{}line\x1b[0m", color)
+        );
+
+        Ok(())
+    }
 }
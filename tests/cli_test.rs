@@ -5,7 +5,7 @@ use anyhow::Result;
 use tempdir::TempDir;
 use wiremock::{
     http::HeaderValue,
-    matchers::{method, path, query_param},
+    matchers::{body_string_contains, header, method, path, query_param},
     Mock, MockServer, ResponseTemplate,
 };
 
@@ -176,3 +176,2250 @@ async fn test_that_subjob_regex_is_anchored_to_beginning_of_line() -> Result<()>
 
     Ok(())
 }
+
+async fn mount_crumb_issuer(mock_server: &MockServer) {
+    Mock::given(method("GET"))
+        .and(path("/crumbIssuer/api/json"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(serde_json::json!({
+                    "_class": "hudson.security.csrf.DefaultCrumbIssuer",
+                    "crumb": "abc123",
+                    "crumbRequestField": "Jenkins-Crumb",
+                }))
+                .append_header("set-cookie", "JSESSIONID.1234=node0xyz; Path=/; HttpOnly"),
+        )
+        .expect(1)
+        .mount(mock_server)
+        .await;
+}
+
+async fn mount_queue_item(mock_server: &MockServer, job_path: &str) {
+    Mock::given(method("GET"))
+        .and(path("/queue/item/1/api/json"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "task": { "name": "hello" },
+            "executable": {
+                "number": 1,
+                "url": format!("{}{job_path}/", mock_server.uri()),
+            },
+        })))
+        .mount(mock_server)
+        .await;
+}
+
+async fn mount_build_result(mock_server: &MockServer, job_path: &str, result: &str) {
+    Mock::given(method("GET"))
+        .and(path(format!("{job_path}/api/json")))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "number": 1,
+            "url": format!("{}{job_path}/", mock_server.uri()),
+            "result": result,
+            "building": false,
+            "duration": 1000,
+            "timestamp": 1676000000000u64,
+        })))
+        .mount(mock_server)
+        .await;
+}
+
+async fn mount_build_trigger(mock_server: &MockServer, build_path: &str) {
+    Mock::given(method("POST"))
+        .and(path(build_path))
+        .respond_with(
+            ResponseTemplate::new(201).append_header(
+                "location",
+                HeaderValue::from_bytes(
+                    format!("{}/queue/item/1/", mock_server.uri()).into_bytes(),
+                )
+                .unwrap(),
+            ),
+        )
+        .mount(mock_server)
+        .await;
+}
+
+async fn mount_job_definitions(
+    mock_server: &MockServer,
+    job_path: &str,
+    definitions: serde_json::Value,
+) {
+    Mock::given(method("GET"))
+        .and(path(format!("{job_path}/api/json")))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "property": [{
+                "_class": "hudson.model.ParametersDefinitionProperty",
+                "parameterDefinitions": definitions,
+            }],
+        })))
+        .mount(mock_server)
+        .await;
+}
+
+#[tokio::test]
+async fn test_run_sends_crumb() -> Result<()> {
+    let testenv = setup_test().await?;
+
+    mount_job_definitions(
+        &testenv.mock_server,
+        "/job/hello",
+        serde_json::json!([{ "_class": "hudson.model.StringParameterDefinition", "name": "A" }]),
+    )
+    .await;
+
+    mount_crumb_issuer(&testenv.mock_server).await;
+    Mock::given(method("POST"))
+        .and(path("/job/hello/buildWithParameters"))
+        .and(header("Jenkins-Crumb", "abc123"))
+        .and(header("cookie", "JSESSIONID.1234=node0xyz"))
+        .respond_with(
+            ResponseTemplate::new(201).append_header(
+                "location",
+                HeaderValue::from_bytes(
+                    format!("{}/queue/item/1/", testenv.mock_server.uri()).into_bytes(),
+                )
+                .unwrap(),
+            ),
+        )
+        .expect(1)
+        .mount(&testenv.mock_server)
+        .await;
+    mount_queue_item(&testenv.mock_server, "/job/hello/1").await;
+    mount_job(&testenv.mock_server, "/job/hello/1", "done").await;
+    mount_build_result(&testenv.mock_server, "/job/hello/1", "SUCCESS").await;
+
+    let mut cmd = assert_cmd::Command::cargo_bin("jenkins").unwrap();
+    let output = cmd
+        .args([
+            "run",
+            &format!("{}/job/hello", testenv.mock_server.uri()),
+            "A=1",
+        ])
+        .env("JENKINS_CLI_CONFIG_PATH", testenv.cfg_path)
+        .output()?;
+    let stdout = String::from_utf8(output.stdout)?;
+
+    assert!(output.status.success());
+    assert!(stdout.contains("hello #1: done"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_run_without_crumb_issuer() -> Result<()> {
+    let testenv = setup_test().await?;
+
+    mount_job_definitions(&testenv.mock_server, "/job/hello", serde_json::json!([])).await;
+
+    Mock::given(method("POST"))
+        .and(path("/job/hello/build"))
+        .respond_with(ResponseTemplate::new(403))
+        .mount(&testenv.mock_server)
+        .await;
+
+    let mut cmd = assert_cmd::Command::cargo_bin("jenkins").unwrap();
+    let output = cmd
+        .args(["run", &format!("{}/job/hello", testenv.mock_server.uri())])
+        .env("JENKINS_CLI_CONFIG_PATH", testenv.cfg_path)
+        .output()?;
+    let stderr = String::from_utf8(output.stderr)?;
+
+    assert!(!output.status.success());
+    assert!(stderr.contains("crumb issuer is disabled"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_status_exit_code() -> Result<()> {
+    let testenv = setup_test().await?;
+
+    Mock::given(method("GET"))
+        .and(path("/job/hello/lastBuild/api/json"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "number": 7,
+            "url": format!("{}/job/hello/7/", testenv.mock_server.uri()),
+            "result": "FAILURE",
+            "building": false,
+            "duration": 65000,
+            "timestamp": 1676000000000u64,
+            "actions": [
+                { "causes": [{ "shortDescription": "Started by user test" }] },
+                {},
+            ],
+        })))
+        .mount(&testenv.mock_server)
+        .await;
+
+    let mut cmd = assert_cmd::Command::cargo_bin("jenkins").unwrap();
+    let output = cmd
+        .args(["status", "/job/hello"])
+        .env("JENKINS_CLI_CONFIG_PATH", testenv.cfg_path)
+        .output()?;
+    let stdout = String::from_utf8(output.stdout)?;
+
+    assert_eq!(output.status.code(), Some(11));
+    assert!(stdout.contains("hello #7\nresult: FAILURE\nbuilding: false\n"));
+    assert!(stdout.contains("started: 2023-02-10T03:33:20Z"));
+    assert!(stdout.contains("duration: 1m 5s"));
+    assert!(stdout.contains("cause: Started by user test"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_run_exits_with_build_result() -> Result<()> {
+    let testenv = setup_test().await?;
+
+    mount_job_definitions(&testenv.mock_server, "/job/hello", serde_json::json!([])).await;
+
+    mount_crumb_issuer(&testenv.mock_server).await;
+    mount_build_trigger(&testenv.mock_server, "/job/hello/build").await;
+    mount_queue_item(&testenv.mock_server, "/job/hello/1").await;
+    mount_job(&testenv.mock_server, "/job/hello/1", "Finished: UNSTABLE").await;
+    mount_build_result(&testenv.mock_server, "/job/hello/1", "UNSTABLE").await;
+
+    let mut cmd = assert_cmd::Command::cargo_bin("jenkins").unwrap();
+    let output = cmd
+        .args(["run", &format!("{}/job/hello", testenv.mock_server.uri())])
+        .env("JENKINS_CLI_CONFIG_PATH", testenv.cfg_path)
+        .output()?;
+
+    assert_eq!(output.status.code(), Some(10));
+
+    Ok(())
+}
+
+// Mounts a running build whose log never ends
+async fn mount_running_build(mock_server: &MockServer, job_path: &str) {
+    Mock::given(method("GET"))
+        .and(path(format!("{job_path}/logText/progressiveText")))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .append_header("x-more-data", "true")
+                .append_header("x-text-size", "0"),
+        )
+        .mount(mock_server)
+        .await;
+}
+
+// Runs `jenkins` with the args, and sends it SIGINT once it starts tailing
+// Sends SIGINT to the CLI once it prints a line starting with `prefix`
+fn interrupt_after(prefix: &str, args: &[&str], cfg_path: &str) -> Result<std::process::Output> {
+    let mut child = std::process::Command::new(assert_cmd::cargo::cargo_bin("jenkins"))
+        .args(args)
+        .env("JENKINS_CLI_CONFIG_PATH", cfg_path)
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .spawn()?;
+
+    let mut stdout = std::io::BufReader::new(child.stdout.take().unwrap());
+    let mut line = String::new();
+    while !line.starts_with(prefix) {
+        line.clear();
+        if std::io::BufRead::read_line(&mut stdout, &mut line)? == 0 {
+            break;
+        }
+    }
+    std::thread::sleep(std::time::Duration::from_millis(500));
+    std::process::Command::new("kill")
+        .args(["-INT", &child.id().to_string()])
+        .status()?;
+
+    Ok(child.wait_with_output()?)
+}
+
+#[tokio::test]
+async fn test_run_interrupted_while_tailing() -> Result<()> {
+    let testenv = setup_test().await?;
+
+    mount_job_definitions(&testenv.mock_server, "/job/hello", serde_json::json!([])).await;
+    mount_crumb_issuer(&testenv.mock_server).await;
+    mount_build_trigger(&testenv.mock_server, "/job/hello/build").await;
+    mount_queue_item(&testenv.mock_server, "/job/hello/1").await;
+    mount_running_build(&testenv.mock_server, "/job/hello/1").await;
+    // Without --abort-on-interrupt the build is left running
+    Mock::given(method("POST"))
+        .and(path("/job/hello/1/stop"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&testenv.mock_server)
+        .await;
+
+    let url = format!("{}/job/hello", testenv.mock_server.uri());
+    let output = interrupt_after("Tailing job", &["run", &url], &testenv.cfg_path)?;
+    let stderr = String::from_utf8(output.stderr)?;
+
+    assert_eq!(output.status.code(), Some(130));
+    assert!(stderr.contains("Interrupted, hello #1 is still running"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_run_aborts_on_interrupt() -> Result<()> {
+    let testenv = setup_test().await?;
+
+    mount_job_definitions(&testenv.mock_server, "/job/hello", serde_json::json!([])).await;
+    mount_crumb_issuer(&testenv.mock_server).await;
+    mount_build_trigger(&testenv.mock_server, "/job/hello/build").await;
+    mount_queue_item(&testenv.mock_server, "/job/hello/1").await;
+    mount_running_build(&testenv.mock_server, "/job/hello/1").await;
+    Mock::given(method("POST"))
+        .and(path("/job/hello/1/stop"))
+        .and(header("Jenkins-Crumb", "abc123"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&testenv.mock_server)
+        .await;
+
+    let url = format!("{}/job/hello", testenv.mock_server.uri());
+    let output = interrupt_after(
+        "Tailing job",
+        &["run", "--abort-on-interrupt", &url],
+        &testenv.cfg_path,
+    )?;
+    let stderr = String::from_utf8(output.stderr)?;
+
+    assert_eq!(output.status.code(), Some(130));
+    assert!(stderr.contains("Interrupted, aborted hello #1"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_run_interrupted_while_queued() -> Result<()> {
+    let testenv = setup_test().await?;
+
+    mount_job_definitions(&testenv.mock_server, "/job/hello", serde_json::json!([])).await;
+    mount_crumb_issuer(&testenv.mock_server).await;
+    mount_build_trigger(&testenv.mock_server, "/job/hello/build").await;
+    Mock::given(method("GET"))
+        .and(path("/queue/item/1/api/json"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "why": "Waiting for next available executor",
+        })))
+        .mount(&testenv.mock_server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/queue/cancelItem"))
+        .and(query_param("id", "1"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&testenv.mock_server)
+        .await;
+
+    let url = format!("{}/job/hello", testenv.mock_server.uri());
+    let output = interrupt_after("Waiting for next", &["run", &url], &testenv.cfg_path)?;
+    let stderr = String::from_utf8(output.stderr)?;
+
+    assert_eq!(output.status.code(), Some(130));
+    assert!(stderr.contains("Interrupted, cancelled queue item 1"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_run_no_wait() -> Result<()> {
+    let testenv = setup_test().await?;
+
+    mount_job_definitions(&testenv.mock_server, "/job/hello", serde_json::json!([])).await;
+
+    mount_crumb_issuer(&testenv.mock_server).await;
+    mount_build_trigger(&testenv.mock_server, "/job/hello/build").await;
+
+    let mut cmd = assert_cmd::Command::cargo_bin("jenkins").unwrap();
+    let output = cmd
+        .args([
+            "run",
+            "--no-wait",
+            &format!("{}/job/hello", testenv.mock_server.uri()),
+        ])
+        .env("JENKINS_CLI_CONFIG_PATH", testenv.cfg_path)
+        .output()?;
+    let stdout = String::from_utf8(output.stdout)?;
+
+    assert!(output.status.success());
+    assert_eq!(
+        stdout,
+        format!("{}/queue/item/1/\n", testenv.mock_server.uri())
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_run_cancelled_queue_item() -> Result<()> {
+    let testenv = setup_test().await?;
+
+    mount_job_definitions(&testenv.mock_server, "/job/hello", serde_json::json!([])).await;
+
+    mount_crumb_issuer(&testenv.mock_server).await;
+    mount_build_trigger(&testenv.mock_server, "/job/hello/build").await;
+    Mock::given(method("GET"))
+        .and(path("/queue/item/1/api/json"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "_class": "hudson.model.Queue$LeftItem",
+            "id": 1,
+            "cancelled": true,
+            "executable": null,
+        })))
+        .mount(&testenv.mock_server)
+        .await;
+
+    let mut cmd = assert_cmd::Command::cargo_bin("jenkins").unwrap();
+    let output = cmd
+        .args(["run", &format!("{}/job/hello", testenv.mock_server.uri())])
+        .env("JENKINS_CLI_CONFIG_PATH", testenv.cfg_path)
+        .output()?;
+    let stderr = String::from_utf8(output.stderr)?;
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr.contains("Queue item 1 was cancelled"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_run_queue_timeout_cancels_item() -> Result<()> {
+    let testenv = setup_test().await?;
+
+    mount_job_definitions(&testenv.mock_server, "/job/hello", serde_json::json!([])).await;
+
+    mount_crumb_issuer(&testenv.mock_server).await;
+    mount_build_trigger(&testenv.mock_server, "/job/hello/build").await;
+    Mock::given(method("GET"))
+        .and(path("/queue/item/1/api/json"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "_class": "hudson.model.Queue$BlockedItem",
+            "id": 1,
+            "blocked": true,
+            "stuck": false,
+            "why": "Waiting for next available executor",
+        })))
+        .mount(&testenv.mock_server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/queue/cancelItem"))
+        .and(query_param("id", "1"))
+        .and(header("Jenkins-Crumb", "abc123"))
+        .respond_with(ResponseTemplate::new(204))
+        .expect(1)
+        .mount(&testenv.mock_server)
+        .await;
+
+    let mut cmd = assert_cmd::Command::cargo_bin("jenkins").unwrap();
+    let output = cmd
+        .args([
+            "run",
+            "--queue-timeout",
+            "1s",
+            &format!("{}/job/hello", testenv.mock_server.uri()),
+        ])
+        .env("JENKINS_CLI_CONFIG_PATH", testenv.cfg_path)
+        .output()?;
+    let stdout = String::from_utf8(output.stdout)?;
+    let stderr = String::from_utf8(output.stderr)?;
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stdout.contains("Blocked: Waiting for next available executor"));
+    assert!(stderr.contains("didn't start within 1s, cancelled it"));
+
+    Ok(())
+}
+
+// Crumbs expire with their session, as in a POST after a long tail
+#[tokio::test]
+async fn test_expired_crumb_is_refreshed() -> Result<()> {
+    let testenv = setup_test().await?;
+
+    for crumb in ["abc123", "def456"] {
+        Mock::given(method("GET"))
+            .and(path("/crumbIssuer/api/json"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "crumb": crumb,
+                "crumbRequestField": "Jenkins-Crumb",
+            })))
+            .up_to_n_times(1)
+            .expect(1)
+            .mount(&testenv.mock_server)
+            .await;
+    }
+    Mock::given(method("POST"))
+        .and(path("/job/hello/1/stop"))
+        .and(header("Jenkins-Crumb", "abc123"))
+        .respond_with(ResponseTemplate::new(403))
+        .expect(1)
+        .mount(&testenv.mock_server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/job/hello/1/stop"))
+        .and(header("Jenkins-Crumb", "def456"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&testenv.mock_server)
+        .await;
+
+    let mut cmd = assert_cmd::Command::cargo_bin("jenkins").unwrap();
+    cmd.args(["stop", "/job/hello/1"])
+        .env("JENKINS_CLI_CONFIG_PATH", &testenv.cfg_path)
+        .assert()
+        .success();
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_stop_escalates_to_term() -> Result<()> {
+    let testenv = setup_test().await?;
+
+    mount_crumb_issuer(&testenv.mock_server).await;
+    Mock::given(method("GET"))
+        .and(path("/job/hello/1/api/json"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "number": 1,
+            "url": format!("{}/job/hello/1/", testenv.mock_server.uri()),
+            "result": null,
+            "building": true,
+            "duration": 0,
+            "timestamp": 1676000000000u64,
+        })))
+        .up_to_n_times(2)
+        .with_priority(1)
+        .mount(&testenv.mock_server)
+        .await;
+    mount_build_result(&testenv.mock_server, "/job/hello/1", "ABORTED").await;
+    for signal in ["stop", "term"] {
+        Mock::given(method("POST"))
+            .and(path(format!("/job/hello/1/{signal}")))
+            .and(header("Jenkins-Crumb", "abc123"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&testenv.mock_server)
+            .await;
+    }
+    Mock::given(method("POST"))
+        .and(path("/job/hello/1/kill"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&testenv.mock_server)
+        .await;
+
+    let mut cmd = assert_cmd::Command::cargo_bin("jenkins").unwrap();
+    let output = cmd
+        .args(["stop", "--kill", "--grace", "1s", "/job/hello/1"])
+        .env("JENKINS_CLI_CONFIG_PATH", testenv.cfg_path)
+        .output()?;
+    let stdout = String::from_utf8(output.stdout)?;
+
+    assert!(output.status.success());
+    assert_eq!(
+        stdout,
+        "Sending stop to hello #1...\nSending term to hello #1...\n"
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_jobs_recursive() -> Result<()> {
+    let testenv = setup_test().await?;
+    let uri = testenv.mock_server.uri();
+
+    Mock::given(method("GET"))
+        .and(path("/api/json"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "jobs": [
+                { "name": "deploy", "url": format!("{uri}/job/deploy/"), "color": "blue" },
+                {
+                    "name": "Folder A",
+                    "url": format!("{uri}/job/Folder%20A/"),
+                    "jobs": [{ "name": "build" }],
+                },
+            ],
+        })))
+        .mount(&testenv.mock_server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/job/Folder%20A/api/json"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "jobs": [
+                {
+                    "name": "build",
+                    "url": format!("{uri}/job/Folder%20A/job/build/"),
+                    "color": "red_anime",
+                },
+                {
+                    "name": "deploy-all",
+                    "url": format!("{uri}/job/Folder%20A/job/deploy-all/"),
+                    "color": "notbuilt",
+                },
+            ],
+        })))
+        .mount(&testenv.mock_server)
+        .await;
+
+    let mut cmd = assert_cmd::Command::cargo_bin("jenkins").unwrap();
+    cmd.args(["jobs", "--recursive"])
+        .env("JENKINS_CLI_CONFIG_PATH", &testenv.cfg_path)
+        .assert()
+        .success()
+        .stdout(concat!(
+            "success    deploy\n",
+            "folder     Folder A\n",
+            "failure*   Folder A » build\n",
+            "notbuilt   Folder A » deploy-all\n",
+        ));
+
+    let mut cmd = assert_cmd::Command::cargo_bin("jenkins").unwrap();
+    cmd.args(["jobs", "--recursive", "--name", "deploy*"])
+        .env("JENKINS_CLI_CONFIG_PATH", &testenv.cfg_path)
+        .assert()
+        .success()
+        .stdout("success    deploy\nnotbuilt   Folder A » deploy-all\n");
+
+    let mut cmd = assert_cmd::Command::cargo_bin("jenkins").unwrap();
+    cmd.args(["jobs"])
+        .env("JENKINS_CLI_CONFIG_PATH", &testenv.cfg_path)
+        .assert()
+        .success()
+        .stdout("success    deploy\nfolder     Folder A\n");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_builds_filters() -> Result<()> {
+    let testenv = setup_test().await?;
+
+    let build = |number: u32, result: &str, timestamp: u64| {
+        serde_json::json!({
+            "number": number,
+            "url": format!("{}/job/mb/job/main/{number}/", testenv.mock_server.uri()),
+            "result": result,
+            "building": false,
+            "duration": 61000,
+            "timestamp": timestamp,
+            "actions": [{ "causes": [{ "shortDescription": "Branch indexing" }] }],
+        })
+    };
+
+    // Pages go through allBuilds, as builds stops at the newest 100
+    let tree = |range: &str| {
+        format!(
+            "allBuilds[number,url,result,building,duration,timestamp,\
+            actions[causes[shortDescription]]]{{{range}}}"
+        )
+    };
+    Mock::given(method("GET"))
+        .and(path("/job/mb/job/main/api/json"))
+        .and(query_param("tree", tree("0,50")))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "allBuilds": [
+                build(3, "SUCCESS", 1676000200000),
+                build(2, "FAILURE", 1676000100000),
+                build(1, "FAILURE", 1676000000000),
+            ],
+        })))
+        .expect(1)
+        .mount(&testenv.mock_server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/job/mb/job/main/api/json"))
+        .and(query_param("tree", tree("3,53")))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "allBuilds": [],
+        })))
+        .mount(&testenv.mock_server)
+        .await;
+
+    let mut cmd = assert_cmd::Command::cargo_bin("jenkins").unwrap();
+    cmd.args([
+        "builds",
+        "/job/mb",
+        "--branch",
+        "main",
+        "--result",
+        "failure",
+        "--since",
+        "2023-02-10T03:34:00Z",
+    ])
+    .env("JENKINS_CLI_CONFIG_PATH", testenv.cfg_path)
+    .assert()
+    .success()
+    .stdout("#2      FAILURE   2023-02-10T03:35:00Z      1m 1s  Branch indexing\n");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_builds_slashed_branch() -> Result<()> {
+    let testenv = setup_test().await?;
+
+    Mock::given(method("GET"))
+        .and(path("/job/mb/job/feature%252Ffoo/api/json"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "allBuilds": [{
+                "number": 4,
+                "url": format!("{}/job/mb/job/feature%252Ffoo/4/", testenv.mock_server.uri()),
+                "result": "SUCCESS",
+                "building": false,
+                "duration": 61000,
+                "timestamp": 1676000200000u64,
+                "actions": [],
+            }],
+        })))
+        .up_to_n_times(1)
+        .with_priority(1)
+        .mount(&testenv.mock_server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/job/mb/job/feature%252Ffoo/api/json"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "allBuilds": [],
+        })))
+        .mount(&testenv.mock_server)
+        .await;
+
+    let mut cmd = assert_cmd::Command::cargo_bin("jenkins").unwrap();
+    let output = cmd
+        .args(["builds", "/job/mb", "--branch", "feature/foo"])
+        .env("JENKINS_CLI_CONFIG_PATH", testenv.cfg_path)
+        .output()?;
+    let stdout = String::from_utf8(output.stdout)?;
+
+    assert!(output.status.success());
+    assert!(stdout.starts_with("#4      SUCCESS"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_params_json_output() -> Result<()> {
+    let testenv = setup_test().await?;
+
+    Mock::given(method("GET"))
+        .and(path("/job/hello/1/api/json"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "actions": [
+                { "_class": "hudson.model.CauseAction" },
+                {
+                    "_class": "hudson.model.ParametersAction",
+                    "parameters": [
+                        {
+                            "_class": "hudson.model.StringParameterValue",
+                            "name": "A",
+                            "value": "1",
+                        },
+                        {
+                            "_class": "hudson.model.BooleanParameterValue",
+                            "name": "B",
+                            "value": true,
+                        },
+                    ],
+                },
+            ],
+        })))
+        .mount(&testenv.mock_server)
+        .await;
+
+    let mut cmd = assert_cmd::Command::cargo_bin("jenkins").unwrap();
+    let output = cmd
+        .args(["params", "/job/hello/1", "--output", "json"])
+        .env("JENKINS_CLI_CONFIG_PATH", &testenv.cfg_path)
+        .output()?;
+    let params: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+
+    assert_eq!(
+        params,
+        serde_json::json!([
+            { "_class": "hudson.model.StringParameterValue", "name": "A", "value": "1" },
+            { "_class": "hudson.model.BooleanParameterValue", "name": "B", "value": true },
+        ])
+    );
+
+    let mut cmd = assert_cmd::Command::cargo_bin("jenkins").unwrap();
+    cmd.args(["params", "/job/hello/1"])
+        .env("JENKINS_CLI_CONFIG_PATH", &testenv.cfg_path)
+        .assert()
+        .success()
+        .stdout("A=\"1\"\nB=true\n");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_tail_json_output() -> Result<()> {
+    let testenv = setup_test().await?;
+
+    mount_job(&testenv.mock_server, "/job/hello/1", "abcd\nefgh").await;
+
+    let mut cmd = assert_cmd::Command::cargo_bin("jenkins").unwrap();
+    let output = cmd
+        .args(["-o", "json", "tail", "/job/hello/1"])
+        .env("JENKINS_CLI_CONFIG_PATH", &testenv.cfg_path)
+        .output()?;
+    let stdout = String::from_utf8(output.stdout)?;
+    let lines: Vec<serde_json::Value> = stdout
+        .lines()
+        .map(serde_json::from_str)
+        .collect::<Result<_, _>>()?;
+
+    assert_eq!(
+        lines,
+        vec![
+            serde_json::json!({
+                "build": "hello #1",
+                "url": format!("{}/job/hello/1/", testenv.mock_server.uri()),
+                "line": "abcd",
+            }),
+            serde_json::json!({
+                "build": "hello #1",
+                "url": format!("{}/job/hello/1/", testenv.mock_server.uri()),
+                "line": "efgh",
+            }),
+        ]
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_rebuild_with_overrides() -> Result<()> {
+    let testenv = setup_test().await?;
+
+    mount_job_definitions(
+        &testenv.mock_server,
+        "/job/hello",
+        serde_json::json!([
+            { "_class": "hudson.model.StringParameterDefinition", "name": "A" },
+            { "_class": "hudson.model.BooleanParameterDefinition", "name": "B" },
+            { "_class": "hudson.model.PasswordParameterDefinition", "name": "C" },
+        ]),
+    )
+    .await;
+
+    Mock::given(method("GET"))
+        .and(path("/job/hello/3/api/json"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "actions": [{
+                "_class": "hudson.model.ParametersAction",
+                "parameters": [
+                    { "_class": "hudson.model.StringParameterValue", "name": "A", "value": "1" },
+                    { "_class": "hudson.model.BooleanParameterValue", "name": "B", "value": true },
+                    { "_class": "hudson.model.PasswordParameterValue", "name": "C", "value": null },
+                ],
+            }],
+        })))
+        .mount(&testenv.mock_server)
+        .await;
+    mount_crumb_issuer(&testenv.mock_server).await;
+    Mock::given(method("POST"))
+        .and(path("/job/hello/buildWithParameters"))
+        .and(body_string_contains("A=2"))
+        .and(body_string_contains("B=true"))
+        .respond_with(
+            ResponseTemplate::new(201).append_header(
+                "location",
+                HeaderValue::from_bytes(
+                    format!("{}/queue/item/1/", testenv.mock_server.uri()).into_bytes(),
+                )
+                .unwrap(),
+            ),
+        )
+        .expect(1)
+        .mount(&testenv.mock_server)
+        .await;
+
+    let mut cmd = assert_cmd::Command::cargo_bin("jenkins").unwrap();
+    let output = cmd
+        .args(["rebuild", "--no-wait", "/job/hello/3", "A=2"])
+        .env("JENKINS_CLI_CONFIG_PATH", testenv.cfg_path)
+        .output()?;
+    let stdout = String::from_utf8(output.stdout)?;
+    let stderr = String::from_utf8(output.stderr)?;
+
+    assert!(output.status.success());
+    assert!(stdout.ends_with("/queue/item/1/\n"));
+    assert!(stderr.contains("C has no value to reuse"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_rebuild_keeps_at_values_as_text() -> Result<()> {
+    let testenv = setup_test().await?;
+
+    mount_job_definitions(
+        &testenv.mock_server,
+        "/job/hello",
+        serde_json::json!([
+            { "_class": "hudson.model.StringParameterDefinition", "name": "NOTIFY" },
+            { "_class": "hudson.model.StringParameterDefinition", "name": "TEAM" },
+        ]),
+    )
+    .await;
+
+    Mock::given(method("GET"))
+        .and(path("/job/hello/3/api/json"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "actions": [{
+                "_class": "hudson.model.ParametersAction",
+                "parameters": [
+                    { "_class": "hudson.model.StringParameterValue", "name": "NOTIFY", "value": "@oncall" },
+                ],
+            }],
+        })))
+        .mount(&testenv.mock_server)
+        .await;
+    mount_crumb_issuer(&testenv.mock_server).await;
+    Mock::given(method("POST"))
+        .and(path("/job/hello/buildWithParameters"))
+        .and(header("content-type", "application/x-www-form-urlencoded"))
+        .and(body_string_contains("NOTIFY=%40oncall"))
+        .and(body_string_contains("TEAM=%40infra"))
+        .respond_with(
+            ResponseTemplate::new(201).append_header(
+                "location",
+                HeaderValue::from_bytes(
+                    format!("{}/queue/item/1/", testenv.mock_server.uri()).into_bytes(),
+                )
+                .unwrap(),
+            ),
+        )
+        .expect(1)
+        .mount(&testenv.mock_server)
+        .await;
+
+    let mut cmd = assert_cmd::Command::cargo_bin("jenkins").unwrap();
+    cmd.args(["rebuild", "--no-wait", "/job/hello/3", "TEAM=@@infra"])
+        .env("JENKINS_CLI_CONFIG_PATH", testenv.cfg_path)
+        .assert()
+        .success();
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_run_validates_params() -> Result<()> {
+    let testenv = setup_test().await?;
+
+    mount_job_definitions(
+        &testenv.mock_server,
+        "/job/hello",
+        serde_json::json!([
+            {
+                "_class": "hudson.model.ChoiceParameterDefinition",
+                "name": "ENV",
+                "choices": ["dev", "prod"],
+                "defaultParameterValue": { "value": "dev" },
+            },
+            {
+                "_class": "hudson.model.StringParameterDefinition",
+                "name": "NAME",
+                "defaultParameterValue": { "value": "world" },
+            },
+        ]),
+    )
+    .await;
+    mount_build_trigger(&testenv.mock_server, "/job/hello/buildWithParameters").await;
+
+    let mut cmd = assert_cmd::Command::cargo_bin("jenkins").unwrap();
+    let output = cmd
+        .args([
+            "run",
+            &format!("{}/job/hello", testenv.mock_server.uri()),
+            "ENV=staging",
+            "NAEM=x",
+        ])
+        .env("JENKINS_CLI_CONFIG_PATH", &testenv.cfg_path)
+        .output()?;
+    let stderr = String::from_utf8(output.stderr)?;
+
+    assert!(!output.status.success());
+    assert!(stderr.contains("ENV must be one of dev, prod, not \"staging\""));
+    assert!(stderr.contains("unknown parameter NAEM"));
+
+    let mut cmd = assert_cmd::Command::cargo_bin("jenkins").unwrap();
+    let output = cmd
+        .args([
+            "run",
+            "--no-wait",
+            &format!("{}/job/hello", testenv.mock_server.uri()),
+            "ENV=prod",
+        ])
+        .env("JENKINS_CLI_CONFIG_PATH", &testenv.cfg_path)
+        .output()?;
+    let stdout = String::from_utf8(output.stdout)?;
+
+    assert!(output.status.success());
+    assert!(stdout.starts_with("Using default NAME=\"world\"\n"));
+
+    let mut cmd = assert_cmd::Command::cargo_bin("jenkins").unwrap();
+    let output = cmd
+        .args([
+            "run",
+            "--no-wait",
+            "--force",
+            &format!("{}/job/hello", testenv.mock_server.uri()),
+            "NAEM=x",
+        ])
+        .env("JENKINS_CLI_CONFIG_PATH", &testenv.cfg_path)
+        .output()?;
+
+    assert!(output.status.success());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_params_definitions() -> Result<()> {
+    let testenv = setup_test().await?;
+
+    mount_job_definitions(
+        &testenv.mock_server,
+        "/job/hello",
+        serde_json::json!([
+            {
+                "_class": "hudson.model.ChoiceParameterDefinition",
+                "name": "ENV",
+                "description": "Where to deploy",
+                "choices": ["dev", "prod"],
+                "defaultParameterValue": { "value": "dev" },
+            },
+            {
+                "_class": "hudson.model.BooleanParameterDefinition",
+                "name": "DRY_RUN",
+                "description": "",
+                "defaultParameterValue": { "value": false },
+            },
+            {
+                "_class": "hudson.model.PasswordParameterDefinition",
+                "name": "TOKEN",
+                "defaultParameterValue": {},
+            },
+            {
+                "_class": "com.cwctravel.hudson.plugins.extended_choice_parameter.ExtendedChoiceParameterDefinition",
+                "name": "REGIONS",
+                "type": "PT_CHECKBOX",
+                "value": "us,eu,ap",
+                "multiSelectDelimiter": ",",
+            },
+        ]),
+    )
+    .await;
+
+    let mut cmd = assert_cmd::Command::cargo_bin("jenkins").unwrap();
+    cmd.args(["params", "--definitions", "/job/hello"])
+        .env("JENKINS_CLI_CONFIG_PATH", &testenv.cfg_path)
+        .assert()
+        .success()
+        .stdout(concat!(
+            "ENV (choice) = \"dev\"\n",
+            "  choices: dev, prod\n",
+            "  Where to deploy\n",
+            "DRY_RUN (boolean) = false\n",
+            "TOKEN (password)\n",
+            "REGIONS (extended-choice)\n",
+            "  choices: us, eu, ap\n",
+        ));
+
+    let mut cmd = assert_cmd::Command::cargo_bin("jenkins").unwrap();
+    let output = cmd
+        .args(["params", "--definitions", "/job/hello", "-o", "json"])
+        .env("JENKINS_CLI_CONFIG_PATH", &testenv.cfg_path)
+        .output()?;
+    let definitions: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+
+    assert_eq!(
+        definitions[0],
+        serde_json::json!({
+            "name": "ENV",
+            "type": "choice",
+            "_class": "hudson.model.ChoiceParameterDefinition",
+            "default": "dev",
+            "choices": ["dev", "prod"],
+            "description": "Where to deploy",
+        })
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_run_uploads_file_parameter() -> Result<()> {
+    let testenv = setup_test().await?;
+
+    mount_job_definitions(
+        &testenv.mock_server,
+        "/job/hello",
+        serde_json::json!([
+            {
+                "_class": "hudson.model.FileParameterDefinition",
+                "name": "ARCHIVE",
+            },
+            {
+                "_class": "hudson.model.StringParameterDefinition",
+                "name": "NAME",
+            },
+        ]),
+    )
+    .await;
+
+    Mock::given(method("POST"))
+        .and(path("/job/hello/buildWithParameters"))
+        .and(body_string_contains(
+            "name=\"ARCHIVE\"; filename=\"archive.txt\"",
+        ))
+        .and(body_string_contains("archive contents"))
+        .and(body_string_contains("name=\"NAME\""))
+        .respond_with(ResponseTemplate::new(201).append_header(
+            "location",
+            format!("{}/queue/item/1/", testenv.mock_server.uri()).as_str(),
+        ))
+        .expect(1)
+        .mount(&testenv.mock_server)
+        .await;
+
+    let archive = testenv._temp_dir.path().join("archive.txt");
+    std::fs::write(&archive, "archive contents")?;
+
+    let mut cmd = assert_cmd::Command::cargo_bin("jenkins").unwrap();
+    let output = cmd
+        .args([
+            "run",
+            "--no-wait",
+            &format!("{}/job/hello", testenv.mock_server.uri()),
+            &format!("ARCHIVE=@{}", archive.display()),
+            "NAME=world",
+        ])
+        .env("JENKINS_CLI_CONFIG_PATH", &testenv.cfg_path)
+        .output()?;
+    let stdout = String::from_utf8(output.stdout)?;
+
+    assert!(output.status.success());
+    assert!(stdout.starts_with(&format!(
+        "Uploading {} as ARCHIVE (16 B)\n",
+        archive.display()
+    )));
+
+    let mut cmd = assert_cmd::Command::cargo_bin("jenkins").unwrap();
+    let output = cmd
+        .args([
+            "run",
+            &format!("{}/job/hello", testenv.mock_server.uri()),
+            &format!("NAME=@{}", archive.display()),
+        ])
+        .env("JENKINS_CLI_CONFIG_PATH", &testenv.cfg_path)
+        .output()?;
+    let stderr = String::from_utf8(output.stderr)?;
+
+    assert!(!output.status.success());
+    assert!(stderr.contains("NAME is not a file parameter"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_run_params_file_from_stdin() -> Result<()> {
+    let testenv = setup_test().await?;
+
+    mount_job_definitions(
+        &testenv.mock_server,
+        "/job/hello",
+        serde_json::json!([
+            {
+                "_class": "hudson.model.StringParameterDefinition",
+                "name": "NAME",
+            },
+            {
+                "_class": "hudson.model.ChoiceParameterDefinition",
+                "name": "ENV",
+                "choices": ["dev", "prod"],
+            },
+        ]),
+    )
+    .await;
+
+    Mock::given(method("POST"))
+        .and(path("/job/hello/buildWithParameters"))
+        .and(body_string_contains("NAME=hello+world"))
+        .and(body_string_contains("ENV=prod"))
+        .respond_with(ResponseTemplate::new(201).append_header(
+            "location",
+            format!("{}/queue/item/1/", testenv.mock_server.uri()).as_str(),
+        ))
+        .expect(1)
+        .mount(&testenv.mock_server)
+        .await;
+
+    // The text output of `params`
+    let mut cmd = assert_cmd::Command::cargo_bin("jenkins").unwrap();
+    cmd.args([
+        "run",
+        "--no-wait",
+        "--params-file",
+        "-",
+        &format!("{}/job/hello", testenv.mock_server.uri()),
+        "ENV=prod",
+    ])
+    .env("JENKINS_CLI_CONFIG_PATH", &testenv.cfg_path)
+    .write_stdin("NAME=\"hello world\"\nENV=\"dev\"\n")
+    .assert()
+    .success();
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_run_repeated_params() -> Result<()> {
+    let testenv = setup_test().await?;
+
+    mount_job_definitions(
+        &testenv.mock_server,
+        "/job/hello",
+        serde_json::json!([
+            {
+                "_class": "com.cwctravel.hudson.plugins.extended_choice_parameter.ExtendedChoiceParameterDefinition",
+                "name": "TARGETS",
+                "type": "PT_MULTI_SELECT",
+                "value": "linux,mac,windows",
+                "multiSelectDelimiter": ",",
+            },
+            {
+                "_class": "hudson.model.StringParameterDefinition",
+                "name": "NAME",
+            },
+        ]),
+    )
+    .await;
+
+    Mock::given(method("POST"))
+        .and(path("/job/hello/buildWithParameters"))
+        .and(body_string_contains("TARGETS=linux%2Cmac"))
+        .and(body_string_contains("NAME=b"))
+        .respond_with(ResponseTemplate::new(201).append_header(
+            "location",
+            format!("{}/queue/item/1/", testenv.mock_server.uri()).as_str(),
+        ))
+        .expect(1)
+        .mount(&testenv.mock_server)
+        .await;
+
+    let mut cmd = assert_cmd::Command::cargo_bin("jenkins").unwrap();
+    let output = cmd
+        .args([
+            "run",
+            "--no-wait",
+            &format!("{}/job/hello", testenv.mock_server.uri()),
+            "TARGETS=linux",
+            "NAME=a",
+            "TARGETS=mac",
+            "NAME=b",
+        ])
+        .env("JENKINS_CLI_CONFIG_PATH", &testenv.cfg_path)
+        .output()?;
+    let stderr = String::from_utf8(output.stderr)?;
+
+    assert!(output.status.success());
+    assert_eq!(
+        stderr,
+        "Warning: NAME takes a single value, using the last one given, \"b\"\n"
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_tail_permalinks() -> Result<()> {
+    let testenv = setup_test().await?;
+
+    mount_job(&testenv.mock_server, "/job/hello/1", "abcd\nefgh").await;
+    mount_build_result(&testenv.mock_server, "/job/hello/lastBuild", "SUCCESS").await;
+    mount_build_result(
+        &testenv.mock_server,
+        "/job/hello/lastFailedBuild",
+        "FAILURE",
+    )
+    .await;
+
+    for job in ["/job/hello", "/job/hello/", "/job/hello/lastFailedBuild"] {
+        let mut cmd = assert_cmd::Command::cargo_bin("jenkins").unwrap();
+        cmd.args(["tail", job])
+            .env("JENKINS_CLI_CONFIG_PATH", &testenv.cfg_path)
+            .assert()
+            .success()
+            .stdout("Tailing hello #1\nhello #1: abcd\nhello #1: efgh\n");
+    }
+
+    let mut cmd = assert_cmd::Command::cargo_bin("jenkins").unwrap();
+    let output = cmd
+        .args(["tail", "/job/hello/lastStableBuild"])
+        .env("JENKINS_CLI_CONFIG_PATH", &testenv.cfg_path)
+        .output()?;
+    let stderr = String::from_utf8(output.stderr)?;
+
+    assert!(!output.status.success());
+    assert!(stderr.contains("Failed to find hello lastStableBuild"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_tail_follow_job() -> Result<()> {
+    let testenv = setup_test().await?;
+
+    mount_job(&testenv.mock_server, "/job/hello/1", "first").await;
+    mount_job(&testenv.mock_server, "/job/hello/2", "second").await;
+
+    // The next build only shows up on the second poll
+    for number in [1, 2] {
+        Mock::given(method("GET"))
+            .and(path("/job/hello/lastBuild/api/json"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "number": number,
+                "url": format!("{}/job/hello/{number}/", testenv.mock_server.uri()),
+                "building": true,
+                "duration": 0,
+                "timestamp": 1676000000000u64,
+            })))
+            .up_to_n_times(if number == 1 { 1 } else { u64::MAX })
+            .mount(&testenv.mock_server)
+            .await;
+    }
+
+    let mut cmd = assert_cmd::Command::cargo_bin("jenkins").unwrap();
+    let output = cmd
+        .args(["tail", "--follow-job", "/job/hello/1"])
+        .env("JENKINS_CLI_CONFIG_PATH", &testenv.cfg_path)
+        .timeout(std::time::Duration::from_secs(7))
+        .output()?;
+    let stdout = String::from_utf8(output.stdout)?;
+
+    // It never exits on its own
+    assert!(!output.status.success());
+    assert_eq!(
+        stdout,
+        "hello #1: first\nTailing hello #2\nhello #2: second\n"
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_tail_follow_job_keeps_polling_after_errors() -> Result<()> {
+    let testenv = setup_test().await?;
+
+    mount_job(&testenv.mock_server, "/job/hello/1", "first").await;
+    mount_job(&testenv.mock_server, "/job/hello/3", "third").await;
+    Mock::given(method("GET"))
+        .and(path("/job/hello/2/logText/progressiveText"))
+        .respond_with(ResponseTemplate::new(500))
+        .mount(&testenv.mock_server)
+        .await;
+
+    // The first poll fails, and the next build fails to tail
+    Mock::given(method("GET"))
+        .and(path("/job/hello/lastBuild/api/json"))
+        .respond_with(ResponseTemplate::new(500))
+        .up_to_n_times(1)
+        .with_priority(1)
+        .mount(&testenv.mock_server)
+        .await;
+    for number in [2, 3] {
+        Mock::given(method("GET"))
+            .and(path("/job/hello/lastBuild/api/json"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "number": number,
+                "url": format!("{}/job/hello/{number}/", testenv.mock_server.uri()),
+                "building": true,
+                "duration": 0,
+                "timestamp": 1676000000000u64,
+            })))
+            .up_to_n_times(if number == 2 { 1 } else { u64::MAX })
+            .mount(&testenv.mock_server)
+            .await;
+    }
+
+    let mut cmd = assert_cmd::Command::cargo_bin("jenkins").unwrap();
+    let output = cmd
+        .args(["tail", "--follow-job", "/job/hello/1"])
+        .env("JENKINS_CLI_CONFIG_PATH", &testenv.cfg_path)
+        .timeout(std::time::Duration::from_secs(9))
+        .output()?;
+    let stdout = String::from_utf8(output.stdout)?;
+    let stderr = String::from_utf8(output.stderr)?;
+
+    assert_eq!(
+        stdout,
+        "hello #1: first\nTailing hello #2\nTailing hello #3\nhello #3: third\n"
+    );
+    assert!(stderr.contains("Warning: Failed to find hello lastBuild"));
+    assert!(stderr.contains("Error: Failed to tail hello #2"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_tail_offsets() -> Result<()> {
+    let testenv = setup_test().await?;
+
+    let log = "l1\nl2\nl3\nl4\n";
+    mount_job(&testenv.mock_server, "/job/hello/1", log).await;
+
+    Mock::given(method("HEAD"))
+        .and(path("/job/hello/1/logText/progressiveText"))
+        .respond_with(ResponseTemplate::new(200).append_header("x-text-size", "12"))
+        .mount(&testenv.mock_server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path("/job/hello/1/logText/progressiveText"))
+        .and(query_param("start", "6"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_string(&log[6..])
+                .append_header("x-more-data", "false")
+                .append_header("x-text-size", "12"),
+        )
+        .mount(&testenv.mock_server)
+        .await;
+
+    for args in [["-n", "2"], ["--start-byte", "6"]] {
+        let mut cmd = assert_cmd::Command::cargo_bin("jenkins").unwrap();
+        cmd.arg("tail")
+            .args(args)
+            .arg("/job/hello/1")
+            .env("JENKINS_CLI_CONFIG_PATH", &testenv.cfg_path)
+            .assert()
+            .success()
+            .stdout("hello #1: l3\nhello #1: l4\n");
+    }
+
+    let mut cmd = assert_cmd::Command::cargo_bin("jenkins").unwrap();
+    cmd.args(["tail", "-n", "10", "/job/hello/1"])
+        .env("JENKINS_CLI_CONFIG_PATH", &testenv.cfg_path)
+        .assert()
+        .success()
+        .stdout("hello #1: l1\nhello #1: l2\nhello #1: l3\nhello #1: l4\n");
+
+    Ok(())
+}
+
+// Offsets count console notes, which Jenkins strips from the text, so they
+// don't match the length of the text returned
+#[tokio::test]
+async fn test_tail_lines_with_console_notes() -> Result<()> {
+    let testenv = setup_test().await?;
+
+    Mock::given(method("HEAD"))
+        .and(path("/job/hello/1/logText/progressiveText"))
+        .respond_with(ResponseTemplate::new(200).append_header("x-text-size", "1000"))
+        .mount(&testenv.mock_server)
+        .await;
+    // The window starts in the middle of a line, and of a note
+    Mock::given(method("GET"))
+        .and(path("/job/hello/1/logText/progressiveText"))
+        .and(query_param("start", "520"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_string("VjUA==\x1b[0m[Pipeline] l1\nl2\nl3\nl4\n")
+                .append_header("x-more-data", "true")
+                .append_header("x-text-size", "1000"),
+        )
+        .mount(&testenv.mock_server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/job/hello/1/logText/progressiveText"))
+        .and(query_param("start", "1000"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_string("l5\n")
+                .append_header("x-more-data", "false")
+                .append_header("x-text-size", "1100"),
+        )
+        .mount(&testenv.mock_server)
+        .await;
+
+    let mut cmd = assert_cmd::Command::cargo_bin("jenkins").unwrap();
+    cmd.args(["tail", "-n", "2", "/job/hello/1"])
+        .env("JENKINS_CLI_CONFIG_PATH", &testenv.cfg_path)
+        .assert()
+        .success()
+        .stdout("hello #1: l3\nhello #1: l4\nhello #1: l5\n");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_tail_no_follow() -> Result<()> {
+    let testenv = setup_test().await?;
+
+    Mock::given(method("GET"))
+        .and(path("/job/hello/1/logText/progressiveText"))
+        .and(query_param("start", "0"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_string("abcd")
+                .append_header("x-more-data", "true")
+                .append_header("x-text-size", "4"),
+        )
+        .expect(1)
+        .mount(&testenv.mock_server)
+        .await;
+
+    let mut cmd = assert_cmd::Command::cargo_bin("jenkins").unwrap();
+    cmd.args(["tail", "--no-follow", "/job/hello/1"])
+        .env("JENKINS_CLI_CONFIG_PATH", &testenv.cfg_path)
+        .assert()
+        .success()
+        .stdout("hello #1: abcd\n");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_tail_downstream_from_api() -> Result<()> {
+    let testenv = setup_test().await?;
+
+    mount_job(
+        &testenv.mock_server,
+        "/job/hello/1",
+        "Starting building: child #2\ndone",
+    )
+    .await;
+
+    Mock::given(method("GET"))
+        .and(path("/job/hello/1/api/json"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "actions": [
+                {
+                    "downstreamBuilds": [
+                        { "jobFullName": "child", "buildNumber": 2 },
+                        { "jobFullName": "other", "buildNumber": 5 },
+                    ],
+                },
+            ],
+        })))
+        .mount(&testenv.mock_server)
+        .await;
+
+    // Found both in the log and through the API, but tailed once
+    Mock::given(method("GET"))
+        .and(path("/job/child/2/logText/progressiveText"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_string("child log")
+                .append_header("x-more-data", "false")
+                .append_header("x-text-size", "9"),
+        )
+        .expect(1)
+        .mount(&testenv.mock_server)
+        .await;
+    mount_job(&testenv.mock_server, "/job/other/5", "other log").await;
+
+    let mut cmd = assert_cmd::Command::cargo_bin("jenkins").unwrap();
+    let output = cmd
+        .args(["tail", "/job/hello/1"])
+        .env("JENKINS_CLI_CONFIG_PATH", &testenv.cfg_path)
+        .output()?;
+    let stdout = String::from_utf8(output.stdout)?;
+
+    assert!(output.status.success());
+    let mut lines: Vec<_> = stdout.lines().collect();
+    lines.sort();
+    assert_eq!(
+        lines,
+        vec![
+            "child #2: child log",
+            "hello #1: Starting building: child #2",
+            "hello #1: done",
+            "other #5: other log",
+        ]
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_tail_skips_unknown_downstream_builds() -> Result<()> {
+    let testenv = setup_test().await?;
+
+    // The slash in the branch name makes the build url ambiguous
+    mount_job(
+        &testenv.mock_server,
+        "/job/hello/1",
+        "Starting building: mb » feature/foo #3\nStarting building: child #2\ndone",
+    )
+    .await;
+    mount_job(&testenv.mock_server, "/job/child/2", "child log").await;
+
+    let mut cmd = assert_cmd::Command::cargo_bin("jenkins").unwrap();
+    let output = cmd
+        .args(["tail", "/job/hello/1"])
+        .env("JENKINS_CLI_CONFIG_PATH", &testenv.cfg_path)
+        .output()?;
+    let stdout = String::from_utf8(output.stdout)?;
+    let stderr = String::from_utf8(output.stderr)?;
+
+    assert!(!output.status.success());
+    assert!(stdout.contains("child #2: child log\n"));
+    assert!(stderr.contains("/job/mb/job/feature/foo/3"));
+    assert!(stderr.contains("Failed to tail 1 of 3 builds"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_tail_throttles_downstream_lookups() -> Result<()> {
+    let testenv = setup_test().await?;
+
+    for (start, line, more) in [(0, "a", "true"), (2, "b", "true"), (4, "c", "false")] {
+        Mock::given(method("GET"))
+            .and(path("/job/hello/1/logText/progressiveText"))
+            .and(query_param("start", start.to_string()))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_string(format!("{line}\n"))
+                    .append_header("x-more-data", more)
+                    .append_header(
+                        "x-text-size",
+                        HeaderValue::from_bytes((start + 2).to_string().into_bytes()).unwrap(),
+                    ),
+            )
+            .mount(&testenv.mock_server)
+            .await;
+    }
+    // Once when tailing starts, and once when the log ends
+    Mock::given(method("GET"))
+        .and(path("/job/hello/1/api/json"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({})))
+        .expect(2)
+        .mount(&testenv.mock_server)
+        .await;
+
+    let mut cmd = assert_cmd::Command::cargo_bin("jenkins").unwrap();
+    cmd.args(["tail", "/job/hello/1"])
+        .env("JENKINS_CLI_CONFIG_PATH", &testenv.cfg_path)
+        .assert()
+        .success()
+        .stdout("hello #1: a\nhello #1: b\nhello #1: c\n");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_tail_tree_grouped() -> Result<()> {
+    let testenv = setup_test().await?;
+
+    mount_job(
+        &testenv.mock_server,
+        "/job/main/1",
+        "Starting building: Folder A » child #2\nmain done",
+    )
+    .await;
+    mount_job(
+        &testenv.mock_server,
+        "/job/Folder%20A/job/child/2",
+        "Starting building: grandchild #3\nchild done",
+    )
+    .await;
+    mount_job(&testenv.mock_server, "/job/grandchild/3", "grandchild done").await;
+
+    let mut cmd = assert_cmd::Command::cargo_bin("jenkins").unwrap();
+    let output = cmd
+        .args([
+            "tail",
+            "--tree",
+            "--group",
+            "--prefix",
+            "short",
+            "/job/main/1",
+        ])
+        .env("JENKINS_CLI_CONFIG_PATH", &testenv.cfg_path)
+        .output()?;
+    let stdout = String::from_utf8(output.stdout)?;
+
+    assert!(output.status.success());
+    assert!(
+        stdout.contains("main #1: Starting building: Folder A » child #2\nmain #1: main done\n")
+    );
+    assert!(
+        stdout.contains("  child #2: Starting building: grandchild #3\n  child #2: child done\n")
+    );
+    assert!(stdout.contains("    grandchild #3: grandchild done\n"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_tail_failing_subjob() -> Result<()> {
+    let testenv = setup_test().await?;
+
+    mount_job(
+        &testenv.mock_server,
+        "/job/main/1",
+        "Starting building: broken #3\nStarting building: child #2\nStarting building: child #2",
+    )
+    .await;
+    // Tailed once, despite being started twice
+    Mock::given(method("GET"))
+        .and(path("/job/child/2/logText/progressiveText"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_string("child done")
+                .append_header("x-more-data", "false")
+                .append_header("x-text-size", "10"),
+        )
+        .expect(1)
+        .mount(&testenv.mock_server)
+        .await;
+
+    let mut cmd = assert_cmd::Command::cargo_bin("jenkins").unwrap();
+    let output = cmd
+        .args(["tail", "/job/main/1"])
+        .env("JENKINS_CLI_CONFIG_PATH", &testenv.cfg_path)
+        .output()?;
+    let stdout = String::from_utf8(output.stdout)?;
+    let stderr = String::from_utf8(output.stderr)?;
+
+    assert!(!output.status.success());
+    assert!(stdout.contains("child #2: child done\n"));
+    assert!(stderr.contains("Failed to tail broken #3"));
+    assert!(stderr.contains("Failed to tail 1 of 3 builds"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_tail_depth() -> Result<()> {
+    let testenv = setup_test().await?;
+
+    mount_job(
+        &testenv.mock_server,
+        "/job/main/1",
+        "Starting building: child #2\nmain done",
+    )
+    .await;
+    mount_job(
+        &testenv.mock_server,
+        "/job/child/2",
+        "Starting building: grandchild #3\nchild done",
+    )
+    .await;
+    mount_job(&testenv.mock_server, "/job/grandchild/3", "grandchild done").await;
+
+    let tail = |args: &[&str]| -> Result<Vec<String>> {
+        let mut cmd = assert_cmd::Command::cargo_bin("jenkins").unwrap();
+        let output = cmd
+            .arg("tail")
+            .args(args)
+            .arg("/job/main/1")
+            .env("JENKINS_CLI_CONFIG_PATH", &testenv.cfg_path)
+            .output()?;
+        assert!(output.status.success());
+        let mut builds: Vec<_> = String::from_utf8(output.stdout)?
+            .lines()
+            .filter_map(|l| l.split_once(':'))
+            .map(|(build, _)| build.to_owned())
+            .collect();
+        builds.sort();
+        builds.dedup();
+        Ok(builds)
+    };
+
+    assert_eq!(tail(&["--no-subjobs"])?, vec!["main #1"]);
+    assert_eq!(tail(&["--depth", "1"])?, vec!["child #2", "main #1"]);
+    assert_eq!(
+        tail(&["--max-concurrent-tails", "1"])?,
+        vec!["child #2", "grandchild #3", "main #1"]
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_stages_failed_logs() -> Result<()> {
+    let testenv = setup_test().await?;
+
+    Mock::given(method("GET"))
+        .and(path("/job/pipeline/12/wfapi/describe"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "id": "12",
+            "name": "#12",
+            "status": "FAILED",
+            "stages": [
+                {
+                    "id": "6",
+                    "name": "Build",
+                    "status": "SUCCESS",
+                    "startTimeMillis": 1676000000000u64,
+                    "durationMillis": 12000,
+                    "pauseDurationMillis": 0,
+                    "_links": { "self": { "href": "/job/pipeline/12/execution/node/6/wfapi/describe" } },
+                },
+                {
+                    "id": "15",
+                    "name": "Test",
+                    "status": "FAILED",
+                    "startTimeMillis": 1676000012000u64,
+                    "durationMillis": 182000,
+                    "pauseDurationMillis": 60000,
+                    "_links": { "self": { "href": "/job/pipeline/12/execution/node/15/wfapi/describe" } },
+                },
+            ],
+        })))
+        .mount(&testenv.mock_server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path("/job/pipeline/12/execution/node/15/wfapi/describe"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "stageFlowNodes": [
+                {
+                    "id": "16",
+                    "name": "Shell Script",
+                    "status": "SUCCESS",
+                    "_links": { "log": { "href": "/job/pipeline/12/execution/node/16/wfapi/log" } },
+                },
+                {
+                    "id": "17",
+                    "name": "Shell Script",
+                    "status": "FAILED",
+                    "_links": { "log": { "href": "/job/pipeline/12/execution/node/17/wfapi/log" } },
+                },
+            ],
+        })))
+        .mount(&testenv.mock_server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path("/job/pipeline/12/execution/node/17/wfapi/log"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "nodeId": "17",
+            "nodeStatus": "FAILED",
+            "text": "<span class=\"pipeline-node-17\">+ make test\nFAIL: test_x\n</span>",
+        })))
+        .mount(&testenv.mock_server)
+        .await;
+
+    let mut cmd = assert_cmd::Command::cargo_bin("jenkins").unwrap();
+    cmd.args(["stages", "--failed-logs", "/job/pipeline/12"])
+        .env("JENKINS_CLI_CONFIG_PATH", &testenv.cfg_path)
+        .assert()
+        .success()
+        .stdout(concat!(
+            "pipeline #12\n",
+            "SUCCESS             12s  Build\n",
+            "FAILED            3m 2s  Test (paused 1m)\n",
+            "  Shell Script (FAILED):\n",
+            "    + make test\n",
+            "    FAIL: test_x\n",
+        ));
+
+    Ok(())
+}
+
+async fn mount_pipeline_stages(mock_server: &MockServer) {
+    let stage = |id: &str, name: &str| {
+        serde_json::json!({
+            "id": id,
+            "name": name,
+            "status": "SUCCESS",
+            "startTimeMillis": 1676000000000u64,
+            "durationMillis": 1000,
+            "_links": { "self": { "href": format!("/job/pipe/3/execution/node/{id}/wfapi/describe") } },
+        })
+    };
+    Mock::given(method("GET"))
+        .and(path("/job/pipe/3/wfapi/describe"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "status": "SUCCESS",
+            "stages": [stage("6", "Build"), stage("10", "Test")],
+        })))
+        .mount(mock_server)
+        .await;
+
+    for (stage, node, text) in [
+        ("6", "7", "compiling\ncompiled\n"),
+        ("10", "11", "<b>testing</b>\n"),
+    ] {
+        Mock::given(method("GET"))
+            .and(path(format!("/job/pipe/3/execution/node/{stage}/wfapi/describe")))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "stageFlowNodes": [{
+                    "id": node,
+                    "name": "Shell Script",
+                    "status": "SUCCESS",
+                    "_links": { "log": { "href": format!("/job/pipe/3/execution/node/{node}/wfapi/log") } },
+                }],
+            })))
+            .mount(mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path(format!("/job/pipe/3/execution/node/{node}/wfapi/log")))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "nodeId": node,
+                "nodeStatus": "SUCCESS",
+                "text": text,
+            })))
+            .mount(mock_server)
+            .await;
+    }
+}
+
+#[tokio::test]
+async fn test_tail_stages() -> Result<()> {
+    let testenv = setup_test().await?;
+    mount_pipeline_stages(&testenv.mock_server).await;
+
+    let mut cmd = assert_cmd::Command::cargo_bin("jenkins").unwrap();
+    cmd.args(["tail", "--stage", "Test", "--prefix", "none", "/job/pipe/3"])
+        .env("JENKINS_CLI_CONFIG_PATH", &testenv.cfg_path)
+        .assert()
+        .success()
+        .stdout("testing\n");
+
+    let mut cmd = assert_cmd::Command::cargo_bin("jenkins").unwrap();
+    let output = cmd
+        .args(["tail", "--stage", "Deploy", "/job/pipe/3"])
+        .env("JENKINS_CLI_CONFIG_PATH", &testenv.cfg_path)
+        .output()?;
+    let stderr = String::from_utf8(output.stderr)?;
+
+    assert!(!output.status.success());
+    assert!(stderr.contains("pipe #3 has no stage named Deploy. Its stages are: Build, Test"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_tail_by_stage() -> Result<()> {
+    let testenv = setup_test().await?;
+    mount_job(
+        &testenv.mock_server,
+        "/job/pipe/3",
+        "Started by user admin\n\
+        [Pipeline] stage\n\
+        [Pipeline] { (Build)\n\
+        compiling\n\
+        [Pipeline] }\n\
+        [Pipeline] // stage\n\
+        post build\n",
+    )
+    .await;
+
+    // Output outside stages is shown too, without a stage
+    let mut cmd = assert_cmd::Command::cargo_bin("jenkins").unwrap();
+    cmd.args(["tail", "--by-stage", "--prefix", "none", "/job/pipe/3"])
+        .env("JENKINS_CLI_CONFIG_PATH", &testenv.cfg_path)
+        .assert()
+        .success()
+        .stdout(concat!(
+            "Started by user admin\n",
+            "[Pipeline] stage\n",
+            "[Build] [Pipeline] { (Build)\n",
+            "[Build] compiling\n",
+            "[Build] [Pipeline] }\n",
+            "[Build] [Pipeline] // stage\n",
+            "post build\n",
+        ))
+        .stderr("");
+
+    let mut cmd = assert_cmd::Command::cargo_bin("jenkins").unwrap();
+    let output = cmd
+        .args(["-o", "json", "tail", "--by-stage", "/job/pipe/3"])
+        .env("JENKINS_CLI_CONFIG_PATH", &testenv.cfg_path)
+        .output()?;
+    let stdout = String::from_utf8(output.stdout)?;
+    let records: Vec<serde_json::Value> = stdout
+        .lines()
+        .map(serde_json::from_str)
+        .collect::<Result<_, _>>()?;
+
+    assert_eq!(records[3]["line"], "compiling");
+    assert_eq!(records[3]["stage"], "Build");
+    assert!(records[6].get("stage").is_none());
+
+    Ok(())
+}
+
+async fn mount_pending_inputs(mock_server: &MockServer, ids: &[&str]) {
+    let inputs: Vec<_> = ids
+        .iter()
+        .map(|id| {
+            serde_json::json!({
+                "id": id,
+                "proceedText": "Proceed",
+                "message": "Deploy to prod?",
+                "inputs": [
+                    {
+                        "type": "StringParameterDefinition",
+                        "name": "REASON",
+                        "description": "Why now",
+                        "definition": { "defaultParameterValue": { "value": "release" } },
+                    },
+                ],
+                "proceedUrl": format!("/job/deploy/12/wfapi/inputSubmit?inputId={id}"),
+                "abortUrl": format!("/job/deploy/12/input/{id}/abort"),
+            })
+        })
+        .collect();
+    Mock::given(method("GET"))
+        .and(path("/job/deploy/12/wfapi/pendingInputActions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(inputs))
+        .mount(mock_server)
+        .await;
+}
+
+#[tokio::test]
+async fn test_input_approve() -> Result<()> {
+    let testenv = setup_test().await?;
+    mount_crumb_issuer(&testenv.mock_server).await;
+    mount_pending_inputs(&testenv.mock_server, &["Deploy"]).await;
+
+    Mock::given(method("POST"))
+        .and(path("/job/deploy/12/input/Deploy/proceed"))
+        .and(header("Jenkins-Crumb", "abc123"))
+        .and(body_string_contains("json="))
+        .and(body_string_contains("REASON"))
+        .and(body_string_contains("hotfix"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&testenv.mock_server)
+        .await;
+
+    let mut cmd = assert_cmd::Command::cargo_bin("jenkins").unwrap();
+    let output = cmd
+        .args(["input", "approve", "/job/deploy/12", "ENV=prod"])
+        .env("JENKINS_CLI_CONFIG_PATH", &testenv.cfg_path)
+        .output()?;
+    let stderr = String::from_utf8(output.stderr)?;
+
+    assert!(!output.status.success());
+    assert!(stderr.contains("ENV is not a parameter of input Deploy. Its parameters are: REASON"));
+
+    let mut cmd = assert_cmd::Command::cargo_bin("jenkins").unwrap();
+    let output = cmd
+        .args([
+            "-o",
+            "json",
+            "input",
+            "approve",
+            "/job/deploy/12",
+            "REASON=hotfix",
+        ])
+        .env("JENKINS_CLI_CONFIG_PATH", &testenv.cfg_path)
+        .output()?;
+    let summary: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+
+    assert!(output.status.success());
+    assert_eq!(summary["build"], "deploy #12");
+    assert_eq!(summary["id"], "Deploy");
+    assert_eq!(summary["action"], "approve");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_input_approve_with_defaults() -> Result<()> {
+    let testenv = setup_test().await?;
+    mount_crumb_issuer(&testenv.mock_server).await;
+    mount_pending_inputs(&testenv.mock_server, &["Deploy"]).await;
+
+    // Inputs with parameters are never approved empty, which would leave
+    // them null in the pipeline
+    Mock::given(method("POST"))
+        .and(path("/job/deploy/12/input/Deploy/proceedEmpty"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&testenv.mock_server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/job/deploy/12/input/Deploy/proceed"))
+        .and(header("Jenkins-Crumb", "abc123"))
+        .and(body_string_contains("REASON"))
+        .and(body_string_contains("release"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&testenv.mock_server)
+        .await;
+
+    let mut cmd = assert_cmd::Command::cargo_bin("jenkins").unwrap();
+    cmd.args(["input", "approve", "/job/deploy/12"])
+        .env("JENKINS_CLI_CONFIG_PATH", &testenv.cfg_path)
+        .assert()
+        .success()
+        .stdout("Approving input Deploy of deploy #12...\n");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_input_abort() -> Result<()> {
+    let testenv = setup_test().await?;
+    mount_crumb_issuer(&testenv.mock_server).await;
+    mount_pending_inputs(&testenv.mock_server, &["Deploy", "Cleanup"]).await;
+
+    Mock::given(method("POST"))
+        .and(path("/job/deploy/12/input/Cleanup/abort"))
+        .and(header("Jenkins-Crumb", "abc123"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&testenv.mock_server)
+        .await;
+
+    let mut cmd = assert_cmd::Command::cargo_bin("jenkins").unwrap();
+    let output = cmd
+        .args(["input", "abort", "/job/deploy/12"])
+        .env("JENKINS_CLI_CONFIG_PATH", &testenv.cfg_path)
+        .output()?;
+    let stderr = String::from_utf8(output.stderr)?;
+
+    assert!(!output.status.success());
+    assert!(stderr.contains(
+        "deploy #12 is waiting for more than one input, pick one with --id: Deploy, Cleanup"
+    ));
+
+    let mut cmd = assert_cmd::Command::cargo_bin("jenkins").unwrap();
+    cmd.args(["input", "abort", "/job/deploy/12", "--id", "Cleanup"])
+        .env("JENKINS_CLI_CONFIG_PATH", &testenv.cfg_path)
+        .assert()
+        .success()
+        .stdout("Aborting input Cleanup of deploy #12...\n");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_tail_shows_pending_input() -> Result<()> {
+    let testenv = setup_test().await?;
+    // Once the log says an input step started, a failed lookup is retried
+    // on the next poll
+    Mock::given(method("GET"))
+        .and(path("/job/deploy/12/wfapi/pendingInputActions"))
+        .respond_with(ResponseTemplate::new(503))
+        .up_to_n_times(1)
+        .with_priority(1)
+        .mount(&testenv.mock_server)
+        .await;
+    mount_pending_inputs(&testenv.mock_server, &["Deploy"]).await;
+
+    Mock::given(method("GET"))
+        .and(path("/job/deploy/12/logText/progressiveText"))
+        .and(query_param("start", "0"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_string("[Pipeline] input\n")
+                .append_header("x-more-data", "true")
+                .append_header("x-text-size", "17"),
+        )
+        .mount(&testenv.mock_server)
+        .await;
+    // Paused for two polls, then approved
+    Mock::given(method("GET"))
+        .and(path("/job/deploy/12/logText/progressiveText"))
+        .and(query_param("start", "17"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .append_header("x-more-data", "true")
+                .append_header("x-text-size", "17"),
+        )
+        .up_to_n_times(2)
+        .with_priority(1)
+        .mount(&testenv.mock_server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/job/deploy/12/logText/progressiveText"))
+        .and(query_param("start", "17"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_string("approved\n")
+                .append_header("x-more-data", "false")
+                .append_header("x-text-size", "26"),
+        )
+        .mount(&testenv.mock_server)
+        .await;
+
+    let mut cmd = assert_cmd::Command::cargo_bin("jenkins").unwrap();
+    let output = cmd
+        .args(["tail", "--no-subjobs", "/job/deploy/12"])
+        .env("JENKINS_CLI_CONFIG_PATH", &testenv.cfg_path)
+        .output()?;
+    let stdout = String::from_utf8(output.stdout)?;
+    let stderr = String::from_utf8(output.stderr)?;
+    let url = format!("{}/job/deploy/12/", testenv.mock_server.uri());
+
+    assert!(output.status.success());
+    assert!(stderr.contains("Warning: Failed to get the pending inputs of deploy #12"));
+    assert_eq!(
+        stdout,
+        format!(
+            "deploy #12: [Pipeline] input\n\
+            deploy #12 is waiting for input Deploy: Deploy to prod?\n  \
+            REASON (string): Why now [default: release]\n\
+            Answer with `jenkins input approve {url} --id Deploy REASON=...` \
+            or `jenkins input abort {url} --id Deploy`\n\
+            deploy #12: approved\n"
+        )
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_tail_throttles_input_lookups() -> Result<()> {
+    let testenv = setup_test().await?;
+    // Looked up on the first quiet poll only, as the log never mentions an
+    // input step
+    Mock::given(method("GET"))
+        .and(path("/job/deploy/12/wfapi/pendingInputActions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([])))
+        .expect(1)
+        .mount(&testenv.mock_server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path("/job/deploy/12/logText/progressiveText"))
+        .and(query_param("start", "0"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_string("sleeping\n")
+                .append_header("x-more-data", "true")
+                .append_header("x-text-size", "9"),
+        )
+        .mount(&testenv.mock_server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/job/deploy/12/logText/progressiveText"))
+        .and(query_param("start", "9"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .append_header("x-more-data", "true")
+                .append_header("x-text-size", "9"),
+        )
+        .up_to_n_times(3)
+        .with_priority(1)
+        .mount(&testenv.mock_server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/job/deploy/12/logText/progressiveText"))
+        .and(query_param("start", "9"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_string("done\n")
+                .append_header("x-more-data", "false")
+                .append_header("x-text-size", "14"),
+        )
+        .mount(&testenv.mock_server)
+        .await;
+
+    let mut cmd = assert_cmd::Command::cargo_bin("jenkins").unwrap();
+    cmd.args(["tail", "--no-subjobs", "/job/deploy/12"])
+        .env("JENKINS_CLI_CONFIG_PATH", &testenv.cfg_path)
+        .assert()
+        .success()
+        .stdout("deploy #12: sleeping\ndeploy #12: done\n");
+
+    Ok(())
+}